@@ -53,8 +53,10 @@ fn main() {
                 .with(MenuItem::Item {
                     name: "Item Disabled".into(),
                     disabled: true, // Disabled entry example
+                    hidden: false,
                     id: Events::Item4,
                     icon: None,
+                    shortcut: None,
                 })
                 .separator()
                 .item("E&xit", Events::Exit),
@@ -77,8 +77,10 @@ fn main() {
                 .with(MenuItem::Item {
                     name: "Item Disabled".into(),
                     disabled: true, // Disabled entry example
+                    hidden: false,
                     id: UserEvents::DisabledItem1,
                     icon: Result::ok(Icon::from_buffer(icon, None, None)),
+                    shortcut: None,
                 })
                 .separator()
                 .item("E&xit", UserEvents::Exit),
@@ -0,0 +1,49 @@
+/// Features supported by the tray of the platform
+///
+/// Methods of unsupported features do nothing, this can be used for adapting
+/// the application instead. See `TrayIcon::capabilities`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// `on_click` events
+    pub click: bool,
+
+    /// `on_double_click` events
+    pub double_click: bool,
+
+    /// `on_right_click` events, without these the tray opens the menu
+    pub right_click: bool,
+
+    /// `on_middle_click` events
+    pub middle_click: bool,
+
+    /// `on_click_event` events with the click position
+    pub click_event: bool,
+
+    /// `on_scroll` events
+    pub scroll: bool,
+
+    /// Opening the menu with `show_menu`
+    pub show_menu: bool,
+
+    /// Title with `set_title`
+    pub title: bool,
+
+    /// Status with `set_status`
+    pub status: bool,
+
+    /// Attention icon and animation with `set_attention_icon`
+    pub attention_icon: bool,
+
+    /// Overlay icon drawn by the tray, otherwise it's drawn on the icon
+    pub native_overlay_icon: bool,
+
+    /// Tooltip title, body markup and icon, otherwise tooltip is plain text
+    pub rich_tooltip: bool,
+
+    /// Icons from icon theme names, otherwise the fallback icon is used
+    pub theme_icons: bool,
+
+    /// Activation token with `get_xdg_activation_token`
+    pub xdg_activation_token: bool,
+}
@@ -0,0 +1,126 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    MenuItemNotFound,
+    /// Menu has more than one item with the same id
+    DuplicateMenuId,
+    /// Icon could not be loaded, contains the reason
+    IconLoadingFailed(String),
+    SenderMissing,
+    IconMissing,
+    /// Operating system call failed, contains the failed operation
+    OsError {
+        context: String,
+        source: Option<Arc<dyn StdError + Send + Sync>>,
+    },
+    /// Session bus could not be connected (Linux)
+    BusUnavailable {
+        source: Arc<dyn StdError + Send + Sync>,
+    },
+    /// Bus name of the icon could not be acquired (Linux)
+    NameRequestDenied {
+        name: String,
+        source: Arc<dyn StdError + Send + Sync>,
+    },
+    /// No system tray (StatusNotifierWatcher) to show the icon (Linux)
+    NoTrayHost {
+        source: Arc<dyn StdError + Send + Sync>,
+    },
+    /// Feature is not supported on this platform, contains the feature
+    UnsupportedOnPlatform(&'static str),
+}
+
+impl Error {
+    /// Operating system error without underlying error
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn os(context: &str) -> Error {
+        Error::OsError {
+            context: context.to_string(),
+            source: None,
+        }
+    }
+
+    /// Operating system error caused by the given error
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) fn os_with_source(
+        context: &str,
+        source: impl StdError + Send + Sync + 'static,
+    ) -> Error {
+        Error::OsError {
+            context: context.to_string(),
+            source: Some(Arc::new(source)),
+        }
+    }
+}
+
+// Why do I need to do this, can't Rust do this automatically?
+impl From<&Error> for Error {
+    fn from(e: &Error) -> Self {
+        e.clone()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MenuItemNotFound => write!(f, "menu item not found"),
+            Error::DuplicateMenuId => write!(f, "menu has more than one item with the same id"),
+            Error::IconLoadingFailed(reason) => write!(f, "icon loading failed: {}", reason),
+            Error::SenderMissing => write!(f, "sender is missing"),
+            Error::IconMissing => write!(f, "icon is missing"),
+            Error::OsError { context, .. } => write!(f, "operating system error: {}", context),
+            Error::BusUnavailable { .. } => write!(f, "session bus unavailable"),
+            Error::NameRequestDenied { name, .. } => {
+                write!(f, "bus name request denied for {}", name)
+            }
+            Error::NoTrayHost { .. } => write!(f, "no system tray"),
+            Error::UnsupportedOnPlatform(feature) => {
+                write!(f, "{} is not supported on this platform", feature)
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        let source = match self {
+            Error::OsError { source, .. } => source.as_ref()?,
+            Error::BusUnavailable { source }
+            | Error::NameRequestDenied { source, .. }
+            | Error::NoTrayHost { source } => source,
+            _ => return None,
+        };
+        Some(source.as_ref())
+    }
+}
+
+/// Errors are equal if they are the same variant with the same message
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.to_string() == other.to_string()
+            && self.source().map(|e| e.to_string()) == other.source().map(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_source() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "no socket");
+        let error = Error::os_with_source("connecting failed", io_error);
+        assert_eq!(
+            error.to_string(),
+            "operating system error: connecting failed"
+        );
+        assert_eq!(error.source().unwrap().to_string(), "no socket");
+        assert_eq!(error.clone(), error);
+        assert_ne!(error, Error::os("connecting failed"));
+        assert!(Error::MenuItemNotFound.source().is_none());
+    }
+}
@@ -1,9 +1,70 @@
+use crate::rgbaimage::{self, RgbaImage};
 use crate::{Error, IconBase};
 use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Source data of the icon, retained for comparing icons by content
+#[derive(Clone, PartialEq)]
+enum IconData {
+    /// Encoded icon file (ICO or PNG)
+    Buffer {
+        buffer: Arc<[u8]>,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+
+    /// Raw RGBA pixels, four bytes per pixel
+    Rgba {
+        width: u32,
+        height: u32,
+        pixels: Arc<[u8]>,
+    },
+
+    /// Same icon in multiple sizes
+    Sizes(Vec<IconData>),
+
+    /// Freedesktop icon theme name
+    ThemeName {
+        name: String,
+        fallback: Option<Box<IconData>>,
+    },
+}
+
+impl IconData {
+    /// Decode to RGBA images of all available sizes
+    fn rgba_images(&self) -> Result<Vec<RgbaImage>, Error> {
+        match self {
+            IconData::Buffer { buffer, .. } => rgbaimage::decode(buffer),
+            IconData::Rgba {
+                width,
+                height,
+                pixels,
+            } => Ok(vec![(*width, *height, pixels.to_vec())]),
+            IconData::Sizes(sizes) => {
+                let mut images = vec![];
+                for data in sizes {
+                    images.extend(data.rgba_images()?);
+                }
+                Ok(images)
+            }
+            IconData::ThemeName {
+                fallback: Some(fallback),
+                ..
+            } => fallback.rgba_images(),
+            IconData::ThemeName {
+                name,
+                fallback: None,
+            } => Err(Error::IconLoadingFailed(format!(
+                "theme icon '{}' has no fallback icon to draw",
+                name
+            ))),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Icon {
-    buffer: Option<&'static [u8]>,
+    data: IconData,
     pub(crate) sys: crate::IconSys,
 }
 
@@ -19,12 +80,106 @@ impl Icon {
         width: Option<u32>,
         height: Option<u32>,
     ) -> Result<Icon, Error> {
+        Icon::from_owned_buffer(buffer.to_vec(), width, height)
+    }
+
+    /// Create icon from ICO or PNG file contents loaded at runtime
+    ///
+    /// Useful for icons that are not embedded in the binary, e.g. icons read
+    /// from disk or downloaded.
+    pub fn from_owned_buffer(
+        buffer: Vec<u8>,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<Icon, Error> {
+        Ok(Icon {
+            sys: crate::IconSys::from_buffer(&buffer, width, height)?,
+            data: IconData::Buffer {
+                buffer: buffer.into(),
+                width,
+                height,
+            },
+        })
+    }
+
+    /// Create icon from raw RGBA pixels
+    ///
+    /// Pixels are given row by row from the top left corner, four bytes (red,
+    /// green, blue, alpha) per pixel.
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Result<Icon, Error> {
+        if width == 0 || height == 0 || rgba.len() != width as usize * height as usize * 4 {
+            return Err(Error::IconLoadingFailed(format!(
+                "expected {} bytes of RGBA pixels for {}x{} icon, got {}",
+                width as usize * height as usize * 4,
+                width,
+                height,
+                rgba.len()
+            )));
+        }
+        Ok(Icon {
+            sys: crate::IconSys::from_rgba(width, height, &rgba)?,
+            data: IconData::Rgba {
+                width,
+                height,
+                pixels: rgba.into(),
+            },
+        })
+    }
+
+    /// Create icon from hand tuned images of different sizes
+    ///
+    /// On Linux all sizes are given to the tray, which picks the one closest
+    /// to its panel size. On Windows the size closest to the system small icon
+    /// size is used, and on MacOS the sizes are added as image representations.
+    pub fn from_sizes(icons: &[Icon]) -> Result<Icon, Error> {
+        if icons.is_empty() {
+            return Err(Error::IconLoadingFailed("no icon sizes given".to_string()));
+        }
+        let sys = icons.iter().map(|icon| &icon.sys).collect::<Vec<_>>();
         Ok(Icon {
-            buffer: Some(buffer),
-            sys: crate::IconSys::from_buffer(buffer, width, height)?,
+            sys: crate::IconSys::from_sizes(&sys)?,
+            data: IconData::Sizes(icons.iter().map(|icon| icon.data.clone()).collect()),
         })
     }
 
+    /// Create icon from freedesktop icon theme name (Linux only)
+    ///
+    /// The tray looks up the icon from the user's icon theme, so the icon
+    /// follows e.g. dark and light themes. The fallback is shown by trays
+    /// that can't find the icon, and is used as is on other platforms. Without
+    /// fallback this fails on other platforms.
+    pub fn from_theme_name(name: &str, fallback: Option<&Icon>) -> Result<Icon, Error> {
+        Ok(Icon {
+            sys: crate::IconSys::from_theme_name(name, fallback.map(|icon| &icon.sys))?,
+            data: IconData::ThemeName {
+                name: name.to_string(),
+                fallback: fallback.map(|icon| Box::new(icon.data.clone())),
+            },
+        })
+    }
+
+    /// Draw the overlay on the bottom right corner of every size of the icon
+    ///
+    /// Used on platforms without native overlay icons.
+    pub(crate) fn with_overlay(&self, overlay: &Icon) -> Result<Icon, Error> {
+        let overlay_image = overlay
+            .data
+            .rgba_images()?
+            .into_iter()
+            .max_by_key(|(width, height, _)| width * height)
+            .ok_or_else(|| Error::IconLoadingFailed("overlay icon is empty".to_string()))?;
+        let icons = self
+            .data
+            .rgba_images()?
+            .iter()
+            .map(|image| {
+                let (width, height, pixels) = rgbaimage::composite_overlay(image, &overlay_image);
+                Icon::from_rgba(width, height, pixels)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Icon::from_sizes(&icons)
+    }
+
     // Sets the icon template mode.
     // This is only applicable on macOS.
     // It allows the icon to be rendered as a template image,
@@ -35,8 +190,82 @@ impl Icon {
     }
 }
 
+/// Icon file format, detected from the magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IconFormat {
+    Ico,
+    Png,
+}
+
+impl IconFormat {
+    pub(crate) fn sniff(buffer: &[u8]) -> Result<IconFormat, Error> {
+        if buffer.starts_with(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']) {
+            Ok(IconFormat::Png)
+        } else if buffer.starts_with(&[0, 0, 1, 0]) {
+            Ok(IconFormat::Ico)
+        } else {
+            Err(Error::IconLoadingFailed(
+                "unrecognized icon format, expected ICO or PNG".to_string(),
+            ))
+        }
+    }
+}
+
 impl PartialEq for Icon {
     fn eq(&self, other: &Self) -> bool {
-        self.buffer == other.buffer
+        self.data == other.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_compares_content() {
+        let pixels = vec![255u8; 2 * 2 * 4];
+        let a = Icon::from_rgba(2, 2, pixels.clone()).unwrap();
+        let b = Icon::from_rgba(2, 2, pixels).unwrap();
+        let c = Icon::from_rgba(2, 2, vec![0u8; 2 * 2 * 4]).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let buffer = include_bytes!("testresource/icon1.ico");
+        let static_icon = Icon::from_buffer(buffer, None, None).unwrap();
+        let owned_icon = Icon::from_owned_buffer(buffer.to_vec(), None, None).unwrap();
+        assert_eq!(static_icon, owned_icon);
+    }
+
+    #[test]
+    fn test_icon_format_sniff() {
+        let ico = include_bytes!("testresource/icon1.ico");
+        let png = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', 0, 0];
+        assert_eq!(IconFormat::sniff(ico), Ok(IconFormat::Ico));
+        assert_eq!(IconFormat::sniff(&png), Ok(IconFormat::Png));
+        assert!(IconFormat::sniff(b"GIF89a").is_err());
+        assert!(Icon::from_owned_buffer(b"GIF89a".to_vec(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_icon_with_overlay() {
+        let icon = Icon::from_rgba(2, 2, [0, 0, 255, 255].repeat(4)).unwrap();
+        let overlay = Icon::from_rgba(1, 1, vec![255, 0, 0, 255]).unwrap();
+        let mut expected = [0, 0, 255, 255].repeat(4);
+        expected[12..16].copy_from_slice(&[255, 0, 0, 255]);
+        assert_eq!(
+            icon.with_overlay(&overlay).unwrap(),
+            Icon::from_sizes(&[Icon::from_rgba(2, 2, expected).unwrap()]).unwrap()
+        );
+
+        let theme_icon = Icon::from_theme_name("network-wireless", None);
+        if let Ok(theme_icon) = theme_icon {
+            assert!(theme_icon.with_overlay(&overlay).is_err());
+        }
+    }
+
+    #[test]
+    fn test_icon_from_rgba_wrong_size() {
+        assert!(Icon::from_rgba(2, 2, vec![0u8; 15]).is_err());
+        assert!(Icon::from_rgba(0, 0, vec![]).is_err());
     }
 }
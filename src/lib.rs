@@ -13,8 +13,13 @@ mod sys;
 #[path = "./sys/linux/mod.rs"]
 mod sys;
 
+mod capabilities;
+mod error;
 mod icon;
 mod menubuilder;
+mod rgbaimage;
+mod shortcut;
+mod tooltip;
 mod trayicon;
 mod trayiconbuilder;
 mod trayiconsender;
@@ -24,12 +29,18 @@ pub trait TrayIconEvent: PartialEq + Clone + 'static + Send + Sync {}
 impl<T> TrayIconEvent for T where T: PartialEq + Clone + 'static + Send + Sync {}
 
 // Public api
+pub use crate::capabilities::Capabilities;
+pub use crate::error::Error;
 pub use crate::icon::Icon;
 pub use crate::menubuilder::{MenuBuilder, MenuItem};
+pub use crate::shortcut::{Modifier, Shortcut};
+pub use crate::tooltip::Tooltip;
 pub use crate::trayicon::TrayIcon;
-pub use crate::trayiconbuilder::Error;
 pub use crate::trayiconbuilder::TrayIconBuilder;
 
+#[cfg(target_os = "linux")]
+pub use crate::sys::TrayIconBuilderExt;
+
 /// Status/visibility state for the tray icon (KDE StatusNotifierItem status)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrayIconStatus {
@@ -41,6 +52,34 @@ pub enum TrayIconStatus {
     Passive,
 }
 
+/// Scroll direction of a scroll event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+/// Mouse button of a click event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickKind {
+    Left,
+    Right,
+    Middle,
+    /// Left double click (Windows only)
+    Double,
+}
+
+/// Click on the tray icon, see `TrayIconBuilder::on_click_event`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickEvent {
+    pub kind: ClickKind,
+    /// Screen position of the click, some trays on Wayland give zero
+    pub x: i32,
+    pub y: i32,
+    /// XDG activation token given by the tray for the click (KDE only)
+    pub xdg_activation_token: Option<String>,
+}
+
 // Each OS specific implementation must export following:
 pub(crate) use crate::sys::{
     // MenuBuilder<T> -> Result<MenuSys<T>, Error>
@@ -59,6 +98,13 @@ pub(crate) use crate::sys::{
     TrayIconSys,
 };
 
+// With async feature, each OS specific implementation must also export:
+#[cfg(feature = "async")]
+pub(crate) use crate::sys::{
+    // TrayIconBuilder<T> -> impl Future<Output = Result<TrayIconSys<T>, Error>>
+    build_trayicon_async,
+};
+
 /// TrayIconSys must implement this
 pub(crate) trait TrayIconBase<T>
 where
@@ -66,7 +112,10 @@ where
 {
     fn set_icon(&mut self, icon: &Icon) -> Result<(), Error>;
     fn set_menu(&mut self, menu: &MenuBuilder<T>) -> Result<(), Error>;
-    fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Error>;
+    fn set_tooltip(&mut self, tooltip: &Tooltip) -> Result<(), Error>;
+
+    /// Features supported by the platform
+    fn capabilities(&self) -> Capabilities;
 
     /// Set title (KDE only)
     ///
@@ -95,17 +144,115 @@ where
         Ok(())
     }
 
+    /// Set the attention icon and animation (KDE only)
+    ///
+    /// Shown by the tray instead of the icon when status is `NeedsAttention`.
+    ///
+    /// On other platforms, this does nothing by default.
+    fn set_attention_icon(
+        &mut self,
+        _icon: Option<&Icon>,
+        _movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Show the icon with overlay icon on top of it
+    ///
+    /// Platforms without native overlay icons draw the overlay on the
+    /// bottom right corner of the icon by default.
+    fn set_overlay_icon(&mut self, icon: &Icon, overlay: Option<&Icon>) -> Result<(), Error> {
+        match overlay {
+            Some(overlay) => self.set_icon(&icon.with_overlay(overlay)?),
+            None => self.set_icon(icon),
+        }
+    }
+
     /// Get the XDG activation token (KDE only)
     fn get_xdg_activation_token(&self) -> Option<String> {
         None
     }
+
+    /// Remove the icon from the tray
+    ///
+    /// Called before the icon is dropped, for reporting errors of the removal.
+    /// By default the removal is left for `Drop`.
+    fn remove(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// TrayIconSys must implement this with async feature
+///
+/// By default these call the blocking methods, platforms that would block
+/// on I/O implement them. Linux implements the blocking methods with these,
+/// so there this is always available.
+#[cfg(any(feature = "async", target_os = "linux"))]
+pub(crate) trait TrayIconBaseAsync<T>: TrayIconBase<T>
+where
+    T: PartialEq + Clone + 'static + Send + Sync,
+{
+    async fn set_icon_async(&mut self, icon: &Icon) -> Result<(), Error> {
+        self.set_icon(icon)
+    }
+
+    async fn set_menu_async(&mut self, menu: &MenuBuilder<T>) -> Result<(), Error> {
+        self.set_menu(menu)
+    }
+
+    async fn set_tooltip_async(&mut self, tooltip: &Tooltip) -> Result<(), Error> {
+        self.set_tooltip(tooltip)
+    }
+
+    async fn set_title_async(&mut self, title: &str) -> Result<(), Error> {
+        self.set_title(title)
+    }
+
+    async fn set_status_async(&mut self, status: TrayIconStatus) -> Result<(), Error> {
+        self.set_status(status)
+    }
+
+    async fn set_attention_icon_async(
+        &mut self,
+        icon: Option<&Icon>,
+        movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        self.set_attention_icon(icon, movie_name)
+    }
+
+    async fn set_overlay_icon_async(
+        &mut self,
+        icon: &Icon,
+        overlay: Option<&Icon>,
+    ) -> Result<(), Error> {
+        self.set_overlay_icon(icon, overlay)
+    }
+
+    async fn remove_async(&mut self) -> Result<(), Error> {
+        self.remove()
+    }
 }
 
 /// IconSys must implement this
 pub(crate) trait IconBase {
     fn from_buffer(
-        buffer: &'static [u8],
+        buffer: &[u8],
         width: Option<u32>,
         height: Option<u32>,
     ) -> Result<IconSys, Error>;
+
+    /// Create from RGBA pixels, length is validated by the caller
+    fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<IconSys, Error>;
+
+    /// Combine icons of different sizes, there is at least one icon
+    fn from_sizes(icons: &[&IconSys]) -> Result<IconSys, Error>;
+
+    /// Icon from freedesktop icon theme name (Linux only)
+    ///
+    /// On other platforms the fallback is used by default.
+    fn from_theme_name(_name: &str, fallback: Option<&IconSys>) -> Result<IconSys, Error> {
+        fallback.cloned().ok_or(Error::UnsupportedOnPlatform(
+            "icon theme name without fallback icon",
+        ))
+    }
 }
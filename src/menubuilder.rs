@@ -1,5 +1,9 @@
-use crate::{Error, Icon, TrayIconEvent};
+use crate::{Error, Icon, Shortcut, TrayIconEvent};
 
+/// Menu item
+///
+/// Names may have `&` before the mnemonic character as in `E&xit`, and `&&`
+/// for a literal `&`. Mnemonics are converted for the platform.
 #[derive(Debug, Clone, PartialEq)]
 pub enum MenuItem<T>
 where
@@ -10,20 +14,36 @@ where
         id: T,
         name: String,
         disabled: bool,
+        hidden: bool,
         icon: Option<Icon>,
+        shortcut: Option<Shortcut>,
     },
     Checkable {
         id: T,
         name: String,
         is_checked: bool,
         disabled: bool,
+        hidden: bool,
+        icon: Option<Icon>,
+        shortcut: Option<Shortcut>,
+    },
+    /// Radio item, checking it unchecks the other items of the same group
+    Radio {
+        id: T,
+        name: String,
+        group: u32,
+        is_checked: bool,
+        disabled: bool,
+        hidden: bool,
         icon: Option<Icon>,
+        shortcut: Option<Shortcut>,
     },
     Submenu {
         id: Option<T>,
         name: String,
         children: MenuBuilder<T>,
         disabled: bool,
+        hidden: bool,
         icon: Option<Icon>,
     },
 }
@@ -72,7 +92,35 @@ where
             id,
             name: name.to_string(),
             disabled: false,
+            hidden: false,
+            icon: None,
+            shortcut: None,
+        });
+        self
+    }
+
+    /// Add item with icon (KDE and MacOS only)
+    pub fn item_with_icon(mut self, name: &str, icon: Icon, id: T) -> Self {
+        self.menu_items.push(MenuItem::Item {
+            id,
+            name: name.to_string(),
+            disabled: false,
+            hidden: false,
+            icon: Some(icon),
+            shortcut: None,
+        });
+        self
+    }
+
+    /// Add item with keyboard shortcut shown next to it
+    pub fn item_with_shortcut(mut self, name: &str, shortcut: Shortcut, id: T) -> Self {
+        self.menu_items.push(MenuItem::Item {
+            id,
+            name: name.to_string(),
+            disabled: false,
+            hidden: false,
             icon: None,
+            shortcut: Some(shortcut),
         });
         self
     }
@@ -83,55 +131,147 @@ where
             name: name.to_string(),
             is_checked,
             disabled: false,
+            hidden: false,
             icon: None,
+            shortcut: None,
         });
         self
     }
 
+    /// Add radio items of a group, the item with id `checked` is checked
+    ///
+    /// Group is an application defined number, unique within the menu.
+    pub fn radio_group(mut self, group: u32, items: &[(&str, T)], checked: &T) -> Self {
+        for (name, id) in items {
+            self.menu_items.push(MenuItem::Radio {
+                id: id.clone(),
+                name: name.to_string(),
+                group,
+                is_checked: id == checked,
+                disabled: false,
+                hidden: false,
+                icon: None,
+                shortcut: None,
+            });
+        }
+        self
+    }
+
     pub fn submenu(mut self, name: &str, menu: MenuBuilder<T>) -> Self {
         self.menu_items.push(MenuItem::Submenu {
             id: None,
             name: name.to_string(),
             children: menu,
             disabled: false,
+            hidden: false,
             icon: None,
         });
         self
     }
 
+    /// Add submenu with icon (KDE and MacOS only)
+    pub fn submenu_with_icon(mut self, name: &str, icon: Icon, menu: MenuBuilder<T>) -> Self {
+        self.menu_items.push(MenuItem::Submenu {
+            id: None,
+            name: name.to_string(),
+            children: menu,
+            disabled: false,
+            hidden: false,
+            icon: Some(icon),
+        });
+        self
+    }
+
     pub(crate) fn build(&self) -> Result<crate::MenuSys<T>, Error> {
         crate::build_menu(self)
     }
 
+    /// Check that item ids are unique, as items are looked up by id
+    pub(crate) fn check_unique_ids(&self) -> Result<(), Error> {
+        let mut ids = vec![];
+        self.collect_ids(&mut ids);
+        for (i, id) in ids.iter().enumerate() {
+            if ids[i + 1..].contains(id) {
+                return Err(Error::DuplicateMenuId);
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_ids<'a>(&'a self, ids: &mut Vec<&'a T>) {
+        for item in &self.menu_items {
+            match item {
+                MenuItem::Item { id, .. }
+                | MenuItem::Checkable { id, .. }
+                | MenuItem::Radio { id, .. } => ids.push(id),
+                MenuItem::Submenu { id, children, .. } => {
+                    ids.extend(id.as_ref());
+                    children.collect_ids(ids);
+                }
+                MenuItem::Separator => {}
+            }
+        }
+    }
+
     /// Get checkable state, if found.
     ///
     /// Prefer maintaining proper application state instead of getting checkable
     /// state with this method.
     pub(crate) fn get_checkable(&mut self, find_id: T) -> Option<bool> {
         let mut found_item = None;
-        let _ = self.mutate_item(find_id, |i| {
-            if let MenuItem::Checkable { is_checked, .. } = i {
+        let _ = self.mutate_item(find_id, |i| match i {
+            MenuItem::Checkable { is_checked, .. } | MenuItem::Radio { is_checked, .. } => {
                 found_item = Some(*is_checked);
                 Ok(())
-            } else {
-                Err(Error::MenuItemNotFound)
             }
+            _ => Err(Error::MenuItemNotFound),
         });
         found_item
     }
 
     /// Set checkable
     ///
+    /// Checking a radio item unchecks the other items of its group.
+    ///
     /// Prefer building a new menu instead of mutating it with this method.
     pub(crate) fn set_checkable(&mut self, id: T, checked: bool) -> Result<(), Error> {
-        self.mutate_item(id, |i| {
-            if let MenuItem::Checkable { is_checked, .. } = i {
+        let mut radio_group = None;
+        self.mutate_item(id.clone(), |i| match i {
+            MenuItem::Checkable { is_checked, .. } => {
                 *is_checked = checked;
                 Ok(())
-            } else {
-                Err(Error::MenuItemNotFound)
             }
-        })
+            MenuItem::Radio {
+                is_checked, group, ..
+            } => {
+                *is_checked = checked;
+                radio_group = Some(*group);
+                Ok(())
+            }
+            _ => Err(Error::MenuItemNotFound),
+        })?;
+        if let (Some(group), true) = (radio_group, checked) {
+            self.uncheck_radio_group(group, &id);
+        }
+        Ok(())
+    }
+
+    /// Uncheck the radio items of the group, except the one with the id
+    fn uncheck_radio_group(&mut self, find_group: u32, except_id: &T) {
+        for item in &mut self.menu_items {
+            match item {
+                MenuItem::Radio {
+                    id,
+                    group,
+                    is_checked,
+                    ..
+                } if *group == find_group && id != except_id => *is_checked = false,
+                MenuItem::Submenu { children, .. } => {
+                    children.uncheck_radio_group(find_group, except_id)
+                }
+                _ => {}
+            }
+        }
     }
 
     /// Set disabled state
@@ -147,6 +287,10 @@ where
                 *d = disabled;
                 Ok(())
             }
+            MenuItem::Radio { disabled: d, .. } => {
+                *d = disabled;
+                Ok(())
+            }
             MenuItem::Submenu { disabled: d, .. } => {
                 *d = disabled;
                 Ok(())
@@ -155,6 +299,22 @@ where
         })
     }
 
+    /// Set hidden state
+    ///
+    /// Prefer building a new menu instead of mutating it with this method.
+    pub(crate) fn set_hidden(&mut self, id: T, hidden: bool) -> Result<(), Error> {
+        self.mutate_item(id, |i| match i {
+            MenuItem::Item { hidden: h, .. }
+            | MenuItem::Checkable { hidden: h, .. }
+            | MenuItem::Radio { hidden: h, .. }
+            | MenuItem::Submenu { hidden: h, .. } => {
+                *h = hidden;
+                Ok(())
+            }
+            MenuItem::Separator => Err(Error::MenuItemNotFound),
+        })
+    }
+
     /// Find item and optionally mutate
     ///
     /// Recursively searches for item with id, and applies function f to item if
@@ -174,6 +334,7 @@ where
         let found_item = self.menu_items.iter_mut().find(|f| match f {
             MenuItem::Item { id, .. } if id == &find_id => true,
             MenuItem::Checkable { id, .. } if id == &find_id => true,
+            MenuItem::Radio { id, .. } if id == &find_id => true,
             MenuItem::Submenu { id, .. } if id.as_ref() == Some(&find_id) => true,
             _ => false,
         });
@@ -213,6 +374,9 @@ pub(crate) mod tests {
         SubItem1,
         SubItem2,
         SubItem3,
+        Eco,
+        Balanced,
+        Performance,
     }
 
     #[test]
@@ -237,8 +401,10 @@ pub(crate) mod tests {
                 .with(MenuItem::Item {
                     name: "Item Disabled".into(),
                     disabled,
+                    hidden: false,
                     id: Events::DisabledItem1,
                     icon: None,
+                    shortcut: None,
                 })
         };
 
@@ -248,4 +414,87 @@ pub(crate) mod tests {
         let _ = old.set_checkable(Events::CheckItem2, true);
         assert_eq!(old, menu_builder(true, true));
     }
+
+    #[test]
+    fn test_menu_set_hidden() {
+        let menu_builder = |hidden| {
+            MenuBuilder::new()
+                .item("Item 1", Events::Item1)
+                .with(MenuItem::Checkable {
+                    name: "Checkable".into(),
+                    is_checked: false,
+                    disabled: false,
+                    hidden,
+                    id: Events::CheckItem1,
+                    icon: None,
+                    shortcut: None,
+                })
+                .submenu(
+                    "Sub Menu",
+                    MenuBuilder::new().with(MenuItem::Item {
+                        name: "Sub item 1".into(),
+                        disabled: false,
+                        hidden,
+                        id: Events::SubItem1,
+                        icon: None,
+                        shortcut: None,
+                    }),
+                )
+                .separator()
+        };
+
+        let mut menu = menu_builder(false);
+        assert_eq!(menu.set_hidden(Events::CheckItem1, true), Ok(()));
+        assert_eq!(menu.set_hidden(Events::SubItem1, true), Ok(()));
+        assert_eq!(menu, menu_builder(true));
+
+        assert_eq!(menu.set_hidden(Events::CheckItem1, false), Ok(()));
+        assert_eq!(menu.set_hidden(Events::SubItem1, false), Ok(()));
+        assert_eq!(menu, menu_builder(false));
+
+        assert_eq!(
+            menu.set_hidden(Events::Item2, true),
+            Err(Error::MenuItemNotFound)
+        );
+    }
+
+    #[test]
+    fn test_menu_duplicate_ids() {
+        let menu = MenuBuilder::new().item("Item 1", Events::Item1).submenu(
+            "Sub Menu",
+            MenuBuilder::new().item("Sub item 1", Events::SubItem1),
+        );
+        assert_eq!(menu.check_unique_ids(), Ok(()));
+
+        let menu = menu.submenu(
+            "Sub Menu 2",
+            MenuBuilder::new().item("Again", Events::Item1),
+        );
+        assert_eq!(menu.check_unique_ids(), Err(Error::DuplicateMenuId));
+    }
+
+    #[test]
+    fn test_menu_radio_group() {
+        let modes = [
+            ("Eco", Events::Eco),
+            ("Balanced", Events::Balanced),
+            ("Performance", Events::Performance),
+        ];
+        let menu_builder = |checked| {
+            MenuBuilder::new()
+                .radio_group(1, &modes[..1], &checked)
+                .submenu(
+                    "More modes",
+                    MenuBuilder::new().radio_group(1, &modes[1..], &checked),
+                )
+                .checkable("This is checkable", true, Events::CheckItem1)
+        };
+
+        let mut menu = menu_builder(Events::Eco);
+        assert_eq!(menu.get_checkable(Events::Eco), Some(true));
+        menu.set_checkable(Events::Balanced, true).unwrap();
+        assert_eq!(menu, menu_builder(Events::Balanced));
+        menu.set_checkable(Events::Eco, true).unwrap();
+        assert_eq!(menu, menu_builder(Events::Eco));
+    }
 }
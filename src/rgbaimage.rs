@@ -0,0 +1,199 @@
+//! Platform independent decoding and compositing of icon images
+
+use crate::{icon::IconFormat, Error};
+
+/// Image as width, height and RGBA pixels, four bytes per pixel
+pub(crate) type RgbaImage = (u32, u32, Vec<u8>);
+
+/// Decode ICO or PNG file to RGBA images, ICO files may contain many sizes
+pub(crate) fn decode(buffer: &[u8]) -> Result<Vec<RgbaImage>, Error> {
+    match IconFormat::sniff(buffer)? {
+        IconFormat::Ico => decode_ico(buffer),
+        IconFormat::Png => Ok(vec![decode_png(buffer)?]),
+    }
+}
+
+/// Decode all images of ICO file, entries may be BMP or PNG
+///
+/// Entries are ordered by decreasing bit depth so that the best entry of each
+/// size comes first. Entries that fail to decode are skipped, it is an error
+/// only if none of them decode.
+fn decode_ico(buffer: &[u8]) -> Result<Vec<RgbaImage>, Error> {
+    let icon_dir = ico::IconDir::read(std::io::Cursor::new(buffer))
+        .map_err(|e| Error::IconLoadingFailed(format!("failed to read ICO file: {}", e)))?;
+    let mut entries = icon_dir.entries().iter().collect::<Vec<_>>();
+    if entries.is_empty() {
+        return Err(Error::IconLoadingFailed(
+            "ICO file has no entries".to_string(),
+        ));
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.bits_per_pixel()));
+    let mut images = vec![];
+    let mut last_error = None;
+    for entry in entries {
+        match entry.decode() {
+            Ok(image) => images.push((image.width(), image.height(), image.rgba_data().to_vec())),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) if images.is_empty() => Err(Error::IconLoadingFailed(format!(
+            "failed to decode ICO entry: {}",
+            e
+        ))),
+        _ => Ok(images),
+    }
+}
+
+/// Decode PNG file to 8-bit RGBA
+fn decode_png(buffer: &[u8]) -> Result<RgbaImage, Error> {
+    let png_error = |e: png::DecodingError| {
+        Error::IconLoadingFailed(format!("failed to decode PNG file: {}", e))
+    };
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(buffer));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(png_error)?;
+    let pixels = &buf[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(Error::IconLoadingFailed(
+                "indexed PNG was not expanded".to_string(),
+            ))
+        }
+    };
+    Ok((info.width, info.height, rgba))
+}
+
+/// Encode RGBA image as PNG file
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, Error> {
+    let png_error = |e: png::EncodingError| {
+        Error::IconLoadingFailed(format!("failed to encode PNG file: {}", e))
+    };
+    let (width, height, rgba) = image;
+    let mut buffer = vec![];
+    let mut encoder = png::Encoder::new(&mut buffer, *width, *height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(rgba).map_err(png_error)?;
+    writer.finish().map_err(png_error)?;
+    Ok(buffer)
+}
+
+/// Draw the overlay on the bottom right quarter of the image
+///
+/// Overlay is scaled with nearest neighbour sampling to half of the image
+/// size and alpha blended on top of it.
+pub(crate) fn composite_overlay(image: &RgbaImage, overlay: &RgbaImage) -> RgbaImage {
+    let (width, height, mut pixels) = image.clone();
+    let (overlay_width, overlay_height, overlay_pixels) = overlay;
+    let target_width = (width / 2).max(1);
+    let target_height = (height / 2).max(1);
+    let offset_x = width - target_width;
+    let offset_y = height - target_height;
+
+    for y in 0..target_height {
+        for x in 0..target_width {
+            let src_x = x * overlay_width / target_width;
+            let src_y = y * overlay_height / target_height;
+            let src = ((src_y * overlay_width + src_x) * 4) as usize;
+            let dst = (((offset_y + y) * width + offset_x + x) * 4) as usize;
+
+            let src_alpha = overlay_pixels[src + 3] as f32 / 255.0;
+            let dst_alpha = pixels[dst + 3] as f32 / 255.0;
+            let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+            if out_alpha <= 0.0 {
+                continue;
+            }
+            for c in 0..3 {
+                let src_c = overlay_pixels[src + c] as f32;
+                let dst_c = pixels[dst + c] as f32;
+                let out_c = (src_c * src_alpha + dst_c * dst_alpha * (1.0 - src_alpha)) / out_alpha;
+                pixels[dst + c] = out_c.round() as u8;
+            }
+            pixels[dst + 3] = (out_alpha * 255.0).round() as u8;
+        }
+    }
+
+    (width, height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_png() {
+        let image = (2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(decode(&encode_png(&image).unwrap()), Ok(vec![image]));
+    }
+
+    #[test]
+    fn test_decode_ico_with_corrupt_entry() {
+        let mut icon_dir = ico::IconDir::new(ico::ResourceType::Icon);
+        for size in [16, 32] {
+            let rgba = vec![255; size as usize * size as usize * 4];
+            let image = ico::IconImage::from_rgba_data(size, size, rgba);
+            icon_dir.add_entry(ico::IconDirEntry::encode_as_png(&image).unwrap());
+        }
+        let mut buffer = vec![];
+        icon_dir.write(&mut buffer).unwrap();
+
+        // Overwrite the PNG data of the second entry after the PNG signature
+        let entry = 6 + 16;
+        let offset = u32::from_le_bytes(buffer[entry + 12..entry + 16].try_into().unwrap());
+        let size = u32::from_le_bytes(buffer[entry + 8..entry + 12].try_into().unwrap());
+        let corrupt = offset as usize + 8..(offset + size) as usize;
+        buffer[corrupt.clone()].fill(0);
+
+        let images = decode(&buffer).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].0, images[0].1), (16, 16));
+
+        // Error only if no entry decodes
+        let first = u32::from_le_bytes(buffer[6 + 12..6 + 16].try_into().unwrap()) as usize;
+        buffer[first + 8..corrupt.start].fill(0);
+        assert!(matches!(decode(&buffer), Err(Error::IconLoadingFailed(_))));
+    }
+
+    #[test]
+    fn test_composite_overlay() {
+        let image = (4, 4, [0, 0, 255, 255].repeat(16));
+        let overlay = (1, 1, vec![255, 0, 0, 255]);
+        let (width, height, pixels) = composite_overlay(&image, &overlay);
+        assert_eq!((width, height), (4, 4));
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let i = (y * 4 + x) * 4;
+                let expected = if x >= 2 && y >= 2 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 0, 255, 255]
+                };
+                assert_eq!(pixels[i..i + 4], expected, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_composite_transparent_overlay() {
+        let image = (2, 2, [10, 20, 30, 255].repeat(4));
+        let overlay = (2, 2, [255, 255, 255, 0].repeat(4));
+        assert_eq!(composite_overlay(&image, &overlay), image);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt::{Display, Formatter};
+
+/// Modifier key of a shortcut
+///
+/// On MacOS `Control` is shown as Command and `Super` as Control, as is the
+/// convention for cross-platform shortcuts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Control,
+    Alt,
+    Shift,
+    Super,
+}
+
+/// Keyboard shortcut shown next to a menu item
+///
+/// Key is a key name such as `S`, `F5` or `Delete`. The shortcut is only
+/// shown, the application handles the key press itself. On MacOS the
+/// shortcut also works while the menu is open, but only single character keys
+/// are shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    pub modifiers: Vec<Modifier>,
+    pub key: String,
+}
+
+impl Shortcut {
+    pub fn new(modifiers: &[Modifier], key: &str) -> Shortcut {
+        Shortcut {
+            modifiers: modifiers.to_vec(),
+            key: key.to_string(),
+        }
+    }
+
+    /// Modifiers and key as in dbusmenu `shortcut` property
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn to_dbusmenu(&self) -> Vec<String> {
+        self.modifiers
+            .iter()
+            .map(|modifier| {
+                match modifier {
+                    Modifier::Control => "Control",
+                    Modifier::Alt => "Alt",
+                    Modifier::Shift => "Shift",
+                    Modifier::Super => "Super",
+                }
+                .to_string()
+            })
+            .chain(std::iter::once(self.key.clone()))
+            .collect()
+    }
+}
+
+/// Shortcut as shown on Windows, e.g. `Ctrl+S`
+impl Display for Shortcut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for modifier in &self.modifiers {
+            let name = match modifier {
+                Modifier::Control => "Ctrl",
+                Modifier::Alt => "Alt",
+                Modifier::Shift => "Shift",
+                Modifier::Super => "Win",
+            };
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Convert `&` mnemonics of the label to `_` mnemonics used by dbusmenu
+///
+/// Literal `_` is escaped as `__` and `&&` is a literal `&`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn mnemonic_to_underscore(label: &str) -> String {
+    let mut result = String::with_capacity(label.len());
+    let mut chars = label.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' if chars.peek() == Some(&'&') => {
+                chars.next();
+                result.push('&');
+            }
+            '&' => result.push('_'),
+            '_' => result.push_str("__"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Remove `&` mnemonics of the label, `&&` is a literal `&`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn strip_mnemonic(label: &str) -> String {
+    let mut result = String::with_capacity(label.len());
+    let mut chars = label.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' if chars.peek() == Some(&'&') => {
+                chars.next();
+                result.push('&');
+            }
+            '&' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(mnemonic_to_underscore("E&xit"), "E_xit");
+        assert_eq!(mnemonic_to_underscore("Save && E&xit"), "Save & E_xit");
+        assert_eq!(mnemonic_to_underscore("snake_case"), "snake__case");
+        assert_eq!(strip_mnemonic("E&xit"), "Exit");
+        assert_eq!(strip_mnemonic("Save && E&xit"), "Save & Exit");
+        assert_eq!(strip_mnemonic("snake_case"), "snake_case");
+    }
+
+    #[test]
+    fn test_shortcut() {
+        let shortcut = Shortcut::new(&[Modifier::Control, Modifier::Shift], "S");
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+S");
+        assert_eq!(shortcut.to_dbusmenu(), vec!["Control", "Shift", "S"]);
+    }
+}
@@ -23,78 +23,203 @@ where
     T: crate::TrayIconEvent,
 {
     menu_sys: super::super::MenuSys<T>,
+    revision: u32,
 }
 
-impl<T> DbusMenu<T>
+/// Change between the old and the new menu, see `DbusMenu::update`
+#[derive(Debug, PartialEq)]
+pub enum MenuUpdate {
+    Unchanged,
+    /// Only properties of items changed, as in `ItemsPropertiesUpdated`
+    Properties {
+        updated: Vec<(i32, HashMap<String, OwnedValue>)>,
+        removed: Vec<(i32, Vec<String>)>,
+    },
+    /// Items were added, removed or moved, contains the new layout revision
+    Layout(u32),
+}
+
+/// Properties of a single item as in `GetLayout` and `GetGroupProperties`
+fn item_properties<T>(item: &super::super::MenuItemData<T>) -> HashMap<String, OwnedValue>
 where
     T: crate::TrayIconEvent,
 {
-    pub fn new(menu_sys: super::super::MenuSys<T>) -> Self {
-        DbusMenu { menu_sys }
+    let mut properties = HashMap::new();
+    if item.is_separator {
+        properties.insert(
+            "type".to_string(),
+            OwnedValue::try_from(Value::new("separator")).unwrap(),
+        );
+        return properties;
     }
 
-    fn build_layout_from_items(&self, items: &[super::super::MenuItemData<T>]) -> Vec<OwnedValue> {
-        let mut children = vec![];
+    properties.insert(
+        "label".to_string(),
+        OwnedValue::try_from(Value::new(item.label.as_str())).unwrap(),
+    );
 
-        for item in items {
-            if item.is_separator {
-                let mut properties = HashMap::new();
-                properties.insert(
-                    "type".to_string(),
-                    OwnedValue::try_from(Value::new("separator")).unwrap(),
-                );
+    // Always set the enabled and visible properties explicitly
+    properties.insert(
+        "enabled".to_string(),
+        OwnedValue::try_from(Value::new(!item.is_disabled)).unwrap(),
+    );
+    properties.insert(
+        "visible".to_string(),
+        OwnedValue::try_from(Value::new(!item.is_hidden)).unwrap(),
+    );
 
-                let layout = Layout {
-                    id: item.id,
-                    properties,
-                    children: vec![],
-                };
-                children.push(OwnedValue::try_from(layout).unwrap());
-            } else {
-                let mut properties = HashMap::new();
-                properties.insert(
-                    "label".to_string(),
-                    OwnedValue::try_from(Value::new(item.label.as_str())).unwrap(),
-                );
-
-                // Always set the enabled property explicitly
-                properties.insert(
-                    "enabled".to_string(),
-                    OwnedValue::try_from(Value::new(!item.is_disabled)).unwrap(),
-                );
-
-                if item.is_checkable {
-                    properties.insert(
-                        "toggle-type".to_string(),
-                        OwnedValue::try_from(Value::new("checkbox")).unwrap(),
-                    );
-                    properties.insert(
-                        "toggle-state".to_string(),
-                        OwnedValue::try_from(Value::new(if item.is_checked { 1i32 } else { 0i32 }))
-                            .unwrap(),
-                    );
-                }
+    if let Some(icon_name) = &item.icon_name {
+        properties.insert(
+            "icon-name".to_string(),
+            OwnedValue::try_from(Value::new(icon_name.as_str())).unwrap(),
+        );
+    }
+    if let Some(icon_data) = &item.icon_data {
+        properties.insert(
+            "icon-data".to_string(),
+            OwnedValue::try_from(Value::new(icon_data.as_slice())).unwrap(),
+        );
+    }
 
-                let child_layouts = if !item.children.is_empty() {
-                    properties.insert(
-                        "children-display".to_string(),
-                        OwnedValue::try_from(Value::new("submenu")).unwrap(),
-                    );
-                    self.build_layout_from_items(&item.children)
-                } else {
-                    vec![]
-                };
+    if let Some(shortcut) = &item.shortcut {
+        properties.insert(
+            "shortcut".to_string(),
+            OwnedValue::try_from(Value::new(vec![shortcut.to_dbusmenu()])).unwrap(),
+        );
+    }
+
+    if item.is_checkable {
+        properties.insert(
+            "toggle-type".to_string(),
+            OwnedValue::try_from(Value::new(if item.is_radio { "radio" } else { "checkbox" }))
+                .unwrap(),
+        );
+        properties.insert(
+            "toggle-state".to_string(),
+            OwnedValue::try_from(Value::new(if item.is_checked { 1i32 } else { 0i32 })).unwrap(),
+        );
+    }
 
+    if !item.children.is_empty() {
+        properties.insert(
+            "children-display".to_string(),
+            OwnedValue::try_from(Value::new("submenu")).unwrap(),
+        );
+    }
+
+    properties
+}
+
+/// Flatten the items and their children
+fn collect_items<'a, T>(
+    items: &'a [super::super::MenuItemData<T>],
+    result: &mut Vec<&'a super::super::MenuItemData<T>>,
+) where
+    T: crate::TrayIconEvent,
+{
+    for item in items {
+        result.push(item);
+        collect_items(&item.children, result);
+    }
+}
+
+/// Items have the same ids in the same places, only properties may differ
+fn same_layout<T>(
+    old: &[super::super::MenuItemData<T>],
+    new: &[super::super::MenuItemData<T>],
+) -> bool
+where
+    T: crate::TrayIconEvent,
+{
+    old.len() == new.len()
+        && old.iter().zip(new).all(|(old, new)| {
+            old.id == new.id
+                && old.is_separator == new.is_separator
+                && same_layout(&old.children, &new.children)
+        })
+}
+
+/// Collect changed and removed properties of items with the same layout
+fn diff_properties<T>(
+    old: &[super::super::MenuItemData<T>],
+    new: &[super::super::MenuItemData<T>],
+    updated: &mut Vec<(i32, HashMap<String, OwnedValue>)>,
+    removed: &mut Vec<(i32, Vec<String>)>,
+) where
+    T: crate::TrayIconEvent,
+{
+    for (old, new) in old.iter().zip(new) {
+        let old_properties = item_properties(old);
+        let new_properties = item_properties(new);
+        let gone = old_properties
+            .keys()
+            .filter(|name| !new_properties.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let changed = new_properties
+            .into_iter()
+            .filter(|(name, value)| old_properties.get(name) != Some(value))
+            .collect::<HashMap<_, _>>();
+        if !changed.is_empty() {
+            updated.push((new.id, changed));
+        }
+        if !gone.is_empty() {
+            removed.push((new.id, gone));
+        }
+        diff_properties(&old.children, &new.children, updated, removed);
+    }
+}
+
+impl<T> DbusMenu<T>
+where
+    T: crate::TrayIconEvent,
+{
+    pub fn new(menu_sys: super::super::MenuSys<T>) -> Self {
+        DbusMenu {
+            menu_sys,
+            revision: 0,
+        }
+    }
+
+    /// Replace the menu, returns what changed for the signals
+    ///
+    /// Layout revision is increased only if items were added, removed or
+    /// moved, so that hosts can update an open menu in place.
+    pub fn update(&mut self, menu_sys: super::super::MenuSys<T>) -> MenuUpdate {
+        let update = if !same_layout(&self.menu_sys.items, &menu_sys.items) {
+            self.revision += 1;
+            MenuUpdate::Layout(self.revision)
+        } else {
+            let mut updated = vec![];
+            let mut removed = vec![];
+            diff_properties(
+                &self.menu_sys.items,
+                &menu_sys.items,
+                &mut updated,
+                &mut removed,
+            );
+            if updated.is_empty() && removed.is_empty() {
+                MenuUpdate::Unchanged
+            } else {
+                MenuUpdate::Properties { updated, removed }
+            }
+        };
+        self.menu_sys = menu_sys;
+        update
+    }
+
+    fn build_layout_from_items(&self, items: &[super::super::MenuItemData<T>]) -> Vec<OwnedValue> {
+        items
+            .iter()
+            .map(|item| {
                 let layout = Layout {
                     id: item.id,
-                    properties,
-                    children: child_layouts,
+                    properties: item_properties(item),
+                    children: self.build_layout_from_items(&item.children),
                 };
-                children.push(OwnedValue::try_from(layout).unwrap());
-            }
-        }
-
-        children
+                OwnedValue::try_from(layout).unwrap()
+            })
+            .collect()
     }
 
     fn find_item_by_id<'a>(
@@ -135,7 +260,7 @@ where
             let children = self.build_layout_from_items(&self.menu_sys.items);
 
             Ok((
-                0,
+                self.revision,
                 Layout {
                     id: parent_id,
                     properties: HashMap::new(),
@@ -148,7 +273,7 @@ where
                 let children = self.build_layout_from_items(&item.children);
 
                 Ok((
-                    0,
+                    self.revision,
                     Layout {
                         id: parent_id,
                         properties: HashMap::new(),
@@ -165,17 +290,34 @@ where
 
     async fn get_group_properties(
         &self,
-        _ids: Vec<i32>,
-        _property_names: Vec<String>,
+        ids: Vec<i32>,
+        property_names: Vec<String>,
     ) -> zbus::fdo::Result<Vec<(i32, HashMap<String, OwnedValue>)>> {
-        Ok(Vec::new())
+        // Empty lists mean all items and all properties
+        let mut items = vec![];
+        collect_items(&self.menu_sys.items, &mut items);
+        Ok(items
+            .into_iter()
+            .filter(|item| ids.is_empty() || ids.contains(&item.id))
+            .map(|item| {
+                let mut properties = item_properties(item);
+                if !property_names.is_empty() {
+                    properties.retain(|name, _| property_names.contains(name));
+                }
+                (item.id, properties)
+            })
+            .collect())
     }
 
     async fn get_property(&self, id: i32, name: String) -> zbus::fdo::Result<OwnedValue> {
-        Err(zbus::fdo::Error::InvalidArgs(format!(
-            "Property '{}' for id {} not found",
-            name, id
-        )))
+        self.find_item_by_id(id, &self.menu_sys.items)
+            .and_then(|item| item_properties(item).remove(&name))
+            .ok_or_else(|| {
+                zbus::fdo::Error::InvalidArgs(format!(
+                    "Property '{}' for id {} not found",
+                    name, id
+                ))
+            })
     }
 
     async fn event(
@@ -258,3 +400,69 @@ where
         parent: i32,
     ) -> zbus::Result<()>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::build_menu;
+    use crate::MenuBuilder;
+
+    #[test]
+    fn test_menu_update() {
+        let menu = |checked| {
+            MenuBuilder::new()
+                .item("Item", 1)
+                .checkable("Check", checked, 2)
+        };
+        let mut dbus_menu = DbusMenu::new(build_menu(&menu(false)).unwrap());
+        assert_eq!(
+            dbus_menu.update(build_menu(&menu(false)).unwrap()),
+            MenuUpdate::Unchanged
+        );
+
+        let toggle_state = OwnedValue::try_from(Value::new(1i32)).unwrap();
+        assert_eq!(
+            dbus_menu.update(build_menu(&menu(true)).unwrap()),
+            MenuUpdate::Properties {
+                updated: vec![(
+                    2,
+                    HashMap::from([("toggle-state".to_string(), toggle_state)])
+                )],
+                removed: vec![],
+            }
+        );
+
+        let added = menu(true).item("Added", 3);
+        assert_eq!(
+            dbus_menu.update(build_menu(&added).unwrap()),
+            MenuUpdate::Layout(1)
+        );
+    }
+
+    #[test]
+    fn test_menu_hidden_item() {
+        let mut menu = MenuBuilder::new().item("Item", 1).item("Hidden", 2);
+        menu.set_hidden(2, true).unwrap();
+        let menu_sys = build_menu(&menu).unwrap();
+        let visible = |item| item_properties(&menu_sys.items[item])["visible"].clone();
+        assert_eq!(visible(0), OwnedValue::try_from(Value::new(true)).unwrap());
+        assert_eq!(visible(1), OwnedValue::try_from(Value::new(false)).unwrap());
+
+        // Showing the item again updates only its visible property
+        let mut dbus_menu = DbusMenu::new(menu_sys.clone());
+        menu.set_hidden(2, false).unwrap();
+        assert_eq!(
+            dbus_menu.update(build_menu(&menu).unwrap()),
+            MenuUpdate::Properties {
+                updated: vec![(
+                    2,
+                    HashMap::from([(
+                        "visible".to_string(),
+                        OwnedValue::try_from(Value::new(true)).unwrap()
+                    )])
+                )],
+                removed: vec![],
+            }
+        );
+    }
+}
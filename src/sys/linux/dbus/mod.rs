@@ -1,122 +1,246 @@
 mod canonical_dbus_menu;
 mod status_notifier_item;
 mod status_notifier_watcher;
-use super::kdeicon::KdeIcon;
+use crate::Error;
 pub use canonical_dbus_menu::*;
-pub use status_notifier_item::{StatusNotifierEvent, StatusNotifierItemImpl};
+use futures::channel::oneshot;
+use futures::StreamExt;
+pub use status_notifier_item::{status_name, StatusNotifierEvent, StatusNotifierItemImpl};
 pub use status_notifier_watcher::StatusNotifierWatcherProxy;
-use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use zbus::names::OwnedWellKnownName;
 
-static DBUS_CONNECTION: LazyLock<zbus::Connection> = LazyLock::new(|| {
-    futures::executor::block_on(async {
-        zbus::Connection::session()
-            .await
-            .expect("Failed to connect to session bus")
-    })
-});
+/// Open a session bus connection for a single tray icon
+///
+/// Every tray icon has its own connection, as the item is always served at
+/// `/StatusNotifierItem` of its bus name.
+pub async fn open_dbus_connection() -> Result<zbus::Connection, Error> {
+    zbus::Connection::session()
+        .await
+        .map_err(|e| Error::BusUnavailable {
+            source: Arc::new(e),
+        })
+}
+
+static NEXT_ITEM_NUMBER: AtomicU32 = AtomicU32::new(1);
+
+/// Bus name and object paths of a single tray icon
+///
+/// Every tray icon in the process gets its own bus name. Object paths are the
+/// same for all, the item is registered to the watcher by bus name which
+/// implies the `/StatusNotifierItem` path.
+#[derive(Debug, Clone)]
+pub struct ItemPaths {
+    pub bus_name: String,
+    pub item: String,
+    pub menu: String,
+}
 
-pub fn get_dbus_connection() -> &'static zbus::Connection {
-    &DBUS_CONNECTION
+impl ItemPaths {
+    pub fn next() -> ItemPaths {
+        let n = NEXT_ITEM_NUMBER.fetch_add(1, Ordering::Relaxed);
+        ItemPaths {
+            bus_name: format!("org.kde.StatusNotifierItem-{}-{}", std::process::id(), n),
+            item: "/StatusNotifierItem".to_string(),
+            menu: "/MenuBar".to_string(),
+        }
+    }
+}
+
+/// Registration steps of a single tray icon that succeeded
+///
+/// Only these are undone by `unregister`, the object paths may be served by
+/// another tray icon on the same connection.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Registered {
+    menu: bool,
+    name: bool,
+    item: bool,
 }
 
-pub fn register_dbus_menu_blocking<T>(connection: &zbus::Connection, menu_sys: super::MenuSys<T>)
+/// Error for an object path served already by another tray icon
+fn path_in_use(path: &str) -> Error {
+    Error::os(&format!(
+        "object path {} is in use, only one tray icon can be served per connection",
+        path
+    ))
+}
+
+pub async fn register_dbus_menu<T>(
+    connection: &zbus::Connection,
+    paths: &ItemPaths,
+    registered: &mut Registered,
+    menu_sys: super::MenuSys<T>,
+) -> Result<(), Error>
 where
     T: crate::TrayIconEvent,
 {
-    return futures::executor::block_on(async {
-        let dbus_menu = DbusMenu::new(menu_sys);
-        let _ = connection
-            .object_server()
-            .at("/MenuBar", dbus_menu)
-            .await
-            .unwrap();
-    });
+    let dbus_menu = DbusMenu::new(menu_sys);
+    let added = connection
+        .object_server()
+        .at(paths.menu.as_str(), dbus_menu)
+        .await
+        .map_err(|e| Error::os_with_source("registering the menu object failed", e))?;
+    if !added {
+        return Err(path_in_use(&paths.menu));
+    }
+    registered.menu = true;
+    Ok(())
 }
 
-pub fn register_notifier_item_watcher_blocking(
+/// Register the item object and the item to the StatusNotifierWatcher
+///
+/// Item `id` is set to the requested well-known name. The item is registered
+/// to the watcher by that name, so releasing the name removes the item from
+/// the watcher.
+///
+/// On error the steps done so far are left in `registered` for `unregister`.
+pub async fn register_notifier_item_watcher(
     connection: &zbus::Connection,
-    channel_sender: std::sync::mpsc::Sender<StatusNotifierEvent>,
-    icon_buffer: Option<Vec<u8>>,
-    icon_width: u32,
-    icon_height: u32,
-    tooltip: String,
-    title: String,
-) -> (
-    StatusNotifierWatcherProxy<'static>,
-    Arc<Mutex<KdeIcon>>,
-    Arc<Mutex<String>>,
-    Arc<Mutex<String>>,
-) {
+    paths: &ItemPaths,
+    registered: &mut Registered,
+    mut status_notifier_item: StatusNotifierItemImpl,
+) -> Result<StatusNotifierWatcherProxy<'static>, Error> {
     // Create the StatusNotifierWatcher proxy and register our item
-    return futures::executor::block_on(async {
-        let unique_name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id()); // TODO: make unique
-        let owned_name = OwnedWellKnownName::try_from(unique_name.clone()).unwrap();
-        let _ = connection.request_name(owned_name).await;
-
-        let icon_data = Arc::new(Mutex::new(KdeIcon {
-            argb_pixels: icon_buffer,
-            width: icon_width,
-            height: icon_height,
-        }));
-
-        let tooltip_data = Arc::new(Mutex::new(tooltip));
-        let title_data = Arc::new(Mutex::new(title));
-
-        let status_notifier_item = StatusNotifierItemImpl {
-            id: unique_name.clone(),
-            channel_sender,
-            icon_data: icon_data.clone(),
-            tooltip: tooltip_data.clone(),
-            title: title_data.clone(),
-        };
-        let _ = connection
-            .object_server()
-            .at("/StatusNotifierItem", status_notifier_item)
-            .await
-            .unwrap();
-        let proxy = StatusNotifierWatcherProxy::builder(&connection)
-            .destination("org.kde.StatusNotifierWatcher")
-            .unwrap()
-            .path("/StatusNotifierWatcher")
-            .unwrap()
-            .build()
-            .await
-            .unwrap();
-
-        // println!("Connected to StatusNotifierWatcher");
-
-        // Check if there's a StatusNotifierHost registered
-        match proxy.is_status_notifier_host_registered().await {
-            Ok(_registered) => {
-                //println!("StatusNotifierHost registered: {}", _registered)
-                ()
-            }
-            Err(e) => eprintln!("Failed to check host registration: {:?}", e),
-        }
+    let name_request_denied = |e: zbus::Error| Error::NameRequestDenied {
+        name: paths.bus_name.clone(),
+        source: Arc::new(e),
+    };
+    let owned_name = OwnedWellKnownName::try_from(paths.bus_name.clone())
+        .map_err(|e| name_request_denied(e.into()))?;
+    connection
+        .request_name(owned_name)
+        .await
+        .map_err(name_request_denied)?;
+    registered.name = true;
 
-        match proxy.register_status_notifier_item(&unique_name).await {
-            Ok(_) => {
-                // println!("Successfully registered as: {}", unique_name)
-                ()
-            }
-            Err(e) => println!("Failed to register: {:?}", e),
+    status_notifier_item.id = paths.bus_name.clone();
+    let added = connection
+        .object_server()
+        .at(paths.item.as_str(), status_notifier_item)
+        .await
+        .map_err(|e| Error::os_with_source("registering the item object failed", e))?;
+    if !added {
+        return Err(path_in_use(&paths.item));
+    }
+    registered.item = true;
+    let proxy = StatusNotifierWatcherProxy::builder(connection)
+        .destination("org.kde.StatusNotifierWatcher")
+        .and_then(|builder| builder.path("/StatusNotifierWatcher"))
+        .map_err(|e| Error::os_with_source("creating the watcher proxy failed", e))?
+        .build()
+        .await
+        .map_err(|e| Error::NoTrayHost {
+            source: Arc::new(e),
+        })?;
+
+    // println!("Connected to StatusNotifierWatcher");
+
+    proxy
+        .register_status_notifier_item(&paths.bus_name)
+        .await
+        .map_err(|e| Error::NoTrayHost {
+            source: Arc::new(e),
+        })?;
+
+    // Get the object from the server and emit the NewIcon signal
+    // This tells the tray host that our icon is ready
+    if let Ok(obj) = connection
+        .object_server()
+        .interface::<_, StatusNotifierItemImpl>(paths.item.as_str())
+        .await
+    {
+        // println!("Emitting NewIcon signal to notify tray of icon availability");
+        let emitter = obj.signal_emitter();
+        if let Err(e) = StatusNotifierItemImpl::new_icon(emitter).await {
+            eprintln!("Failed to emit NewIcon signal: {:?}", e);
         }
+    }
 
-        // Get the object from the server and emit the NewIcon signal
-        // This tells the tray host that our icon is ready
-        if let Ok(obj) = connection
-            .object_server()
-            .interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem")
-            .await
-        {
-            // println!("Emitting NewIcon signal to notify tray of icon availability");
-            let emitter = obj.signal_emitter();
-            if let Err(e) = StatusNotifierItemImpl::new_icon(&emitter).await {
-                eprintln!("Failed to emit NewIcon signal: {:?}", e);
-            }
+    Ok(proxy)
+}
+
+/// Remove the objects of the item and release its bus name
+///
+/// Only the steps in `registered` are undone, and they are cleared. Every
+/// step is tried even if some fail, the first error is returned.
+pub async fn unregister<T>(
+    connection: &zbus::Connection,
+    paths: &ItemPaths,
+    registered: &mut Registered,
+) -> Result<(), Error>
+where
+    T: crate::TrayIconEvent,
+{
+    let object_server = connection.object_server();
+    let mut results = vec![];
+    if std::mem::take(&mut registered.item) {
+        let item = object_server
+            .remove::<StatusNotifierItemImpl, _>(paths.item.as_str())
+            .await;
+        results.push(item.map(|_| ()));
+    }
+    if std::mem::take(&mut registered.menu) {
+        let menu = object_server
+            .remove::<DbusMenu<T>, _>(paths.menu.as_str())
+            .await;
+        results.push(menu.map(|_| ()));
+    }
+    if std::mem::take(&mut registered.name) {
+        let name = connection.release_name(paths.bus_name.as_str()).await;
+        results.push(name.map(|_| ()));
+    }
+
+    for result in results {
+        if let Err(e) = result {
+            return Err(Error::os_with_source("unregistering the item failed", e));
         }
+    }
+    Ok(())
+}
 
-        (proxy, icon_data, tooltip_data, title_data)
+/// Re-register the item each time the watcher or a tray host appears
+///
+/// This is the equivalent of handling `TaskbarCreated` on Windows, the item
+/// would otherwise vanish for good when e.g. plasmashell restarts. Runs in a
+/// thread until the returned sender is used or dropped.
+pub fn spawn_watcher_monitor(
+    proxy: StatusNotifierWatcherProxy<'static>,
+    bus_name: String,
+) -> oneshot::Sender<()> {
+    let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+    std::thread::spawn(move || {
+        futures::executor::block_on(async move {
+            let Ok(dbus_proxy) = zbus::fdo::DBusProxy::new(proxy.inner().connection()).await else {
+                return;
+            };
+            let Ok(owner_changed) = dbus_proxy
+                .receive_name_owner_changed_with_args(&[(0, "org.kde.StatusNotifierWatcher")])
+                .await
+            else {
+                return;
+            };
+            let Ok(host_registered) = proxy.receive_status_notifier_host_registered().await else {
+                return;
+            };
+
+            // Watcher started (has a new owner) or a tray host registered
+            let watcher_started = owner_changed.filter_map(|signal| {
+                let started = signal
+                    .args()
+                    .is_ok_and(|args| args.new_owner().as_ref().is_some());
+                futures::future::ready(started.then_some(()))
+            });
+            let mut events = futures::stream::select(watcher_started, host_registered.map(|_| ()))
+                .take_until(stop_receiver);
+
+            // A failed registration is tried again on the next event, there
+            // is no caller to report it to
+            while events.next().await.is_some() {
+                let _ = proxy.register_status_notifier_item(&bus_name).await;
+            }
+        })
     });
+    stop_sender
 }
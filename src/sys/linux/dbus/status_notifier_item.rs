@@ -1,4 +1,5 @@
 use super::super::kdeicon::KdeIcon;
+use crate::{Tooltip, TrayIconStatus};
 use std::sync::{Arc, Mutex};
 use zbus::interface;
 use zbus::object_server::SignalEmitter;
@@ -17,14 +18,36 @@ pub enum StatusNotifierEvent {
     SecondaryActivate(i32, i32),
 }
 
+/// Status as in StatusNotifierItem `Status` property and `NewStatus` signal
+pub fn status_name(status: TrayIconStatus) -> &'static str {
+    match status {
+        TrayIconStatus::Active => "Active",
+        TrayIconStatus::NeedsAttention => "NeedsAttention",
+        TrayIconStatus::Passive => "Passive",
+    }
+}
+
+/// Escape plain text for the markup in ToolTip description
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 // Minimal in-process implementation of `org.kde.StatusNotifierItem` to register
 #[derive(Debug)]
 pub struct StatusNotifierItemImpl {
     pub id: String,
+    pub menu_path: String,
     pub channel_sender: std::sync::mpsc::Sender<StatusNotifierEvent>,
     pub icon_data: Arc<Mutex<KdeIcon>>,
-    pub tooltip: Arc<Mutex<String>>,
+    pub tooltip: Arc<Mutex<Tooltip>>,
     pub title: Arc<Mutex<String>>,
+    pub status: Arc<Mutex<TrayIconStatus>>,
+    pub icon_theme_path: String,
+    pub attention_icon_data: Arc<Mutex<Option<KdeIcon>>>,
+    pub attention_movie_name: Arc<Mutex<String>>,
+    pub overlay_icon_data: Arc<Mutex<Option<KdeIcon>>>,
 }
 
 #[interface(name = "org.kde.StatusNotifierItem")]
@@ -74,18 +97,34 @@ impl StatusNotifierItemImpl {
     /// AttentionIconName property
     #[zbus(property)]
     pub fn attention_icon_name(&self) -> zbus::fdo::Result<String> {
+        if let Ok(attention_icon) = self.attention_icon_data.lock() {
+            if let Some(theme_name) = attention_icon
+                .as_ref()
+                .and_then(|icon| icon.theme_name.as_ref())
+            {
+                return Ok(theme_name.clone());
+            }
+        }
         Ok(String::new())
     }
 
     /// AttentionIconPixmap property
     #[zbus(property)]
     pub fn attention_icon_pixmap(&self) -> zbus::fdo::Result<Vec<(i32, i32, Vec<u8>)>> {
+        if let Ok(attention_icon) = self.attention_icon_data.lock() {
+            if let Some(icon) = attention_icon.as_ref() {
+                return Ok(icon.pixmaps.clone());
+            }
+        }
         Ok(vec![])
     }
 
     /// AttentionMovieName property
     #[zbus(property)]
     pub fn attention_movie_name(&self) -> zbus::fdo::Result<String> {
+        if let Ok(attention_movie_name) = self.attention_movie_name.lock() {
+            return Ok(attention_movie_name.clone());
+        }
         Ok(String::new())
     }
 
@@ -103,7 +142,10 @@ impl StatusNotifierItemImpl {
         // Return empty string so that icon_pixmap is used instead
         // If we return a theme icon name, it will be preferred over the pixmap
         if let Ok(icon_data) = self.icon_data.lock() {
-            if icon_data.argb_pixels.is_some() {
+            if let Some(theme_name) = &icon_data.theme_name {
+                return Ok(theme_name.clone());
+            }
+            if !icon_data.pixmaps.is_empty() {
                 return Ok(String::new());
             }
         }
@@ -115,13 +157,11 @@ impl StatusNotifierItemImpl {
     pub fn icon_pixmap(&self) -> zbus::fdo::Result<Vec<(i32, i32, Vec<u8>)>> {
         // println!("icon_pixmap() called");
 
-        // If we have icon data, use it
+        // If we have icon data, use it. All sizes are given so that the tray
+        // can pick the one closest to its panel size.
         if let Ok(icon_data) = self.icon_data.lock() {
-            if let Some(ref argb_pixmap) = icon_data.argb_pixels {
-                let width = icon_data.width as i32;
-                let height = icon_data.height as i32;
-
-                return Ok(vec![(width, height, argb_pixmap.clone())]);
+            if !icon_data.pixmaps.is_empty() {
+                return Ok(icon_data.pixmaps.clone());
             }
         }
 
@@ -146,7 +186,7 @@ impl StatusNotifierItemImpl {
     /// IconThemePath property
     #[zbus(property)]
     pub fn icon_theme_path(&self) -> zbus::fdo::Result<String> {
-        Ok(String::new())
+        Ok(self.icon_theme_path.clone())
     }
 
     /// Id property
@@ -165,31 +205,43 @@ impl StatusNotifierItemImpl {
     /// Menu property
     #[zbus(property)]
     pub fn menu(&self) -> zbus::fdo::Result<zbus::zvariant::OwnedObjectPath> {
-        Ok(
-            zbus::zvariant::OwnedObjectPath::try_from("/MenuBar").map_err(|_| {
-                zbus::fdo::Error::UnknownProperty("Failed to create object path".to_string())
-            })?,
-        )
+        zbus::zvariant::OwnedObjectPath::try_from(self.menu_path.as_str()).map_err(|_| {
+            zbus::fdo::Error::UnknownProperty("Failed to create object path".to_string())
+        })
     }
 
     /// OverlayIconName property (icon at the corner of the main icon)
     #[zbus(property)]
     pub fn overlay_icon_name(&self) -> zbus::fdo::Result<String> {
-        Ok(String::from(""))
+        if let Ok(overlay_icon) = self.overlay_icon_data.lock() {
+            if let Some(theme_name) = overlay_icon
+                .as_ref()
+                .and_then(|icon| icon.theme_name.as_ref())
+            {
+                return Ok(theme_name.clone());
+            }
+        }
+        Ok(String::new())
     }
 
     /// OverlayIconPixmap property (icon at the corner of the main icon)
     #[zbus(property)]
     pub fn overlay_icon_pixmap(&self) -> zbus::fdo::Result<Vec<(i32, i32, Vec<u8>)>> {
+        if let Ok(overlay_icon) = self.overlay_icon_data.lock() {
+            if let Some(icon) = overlay_icon.as_ref() {
+                return Ok(icon.pixmaps.clone());
+            }
+        }
         Ok(vec![])
     }
 
     /// Status property
     #[zbus(property)]
     pub fn status(&self) -> zbus::fdo::Result<String> {
-        // println!("status() called");
-        // Return "Active" by default, but this can be changed to "NeedsAttention" or "Passive"
-        Ok(String::from("Active"))
+        if let Ok(status) = self.status.lock() {
+            return Ok(status_name(*status).to_string());
+        }
+        Ok(status_name(TrayIconStatus::Active).to_string())
     }
 
     /// Title property
@@ -205,8 +257,24 @@ impl StatusNotifierItemImpl {
     pub fn tool_tip(
         &self,
     ) -> zbus::fdo::Result<(String, Vec<(i32, i32, Vec<u8>)>, String, String)> {
-        let tooltip = self.tooltip.lock().unwrap().clone();
-        Ok((String::new(), vec![], tooltip, String::new()))
+        if let Ok(tooltip) = self.tooltip.lock() {
+            let (icon_name, icon_pixmap) = match &tooltip.icon {
+                Some(icon) => (
+                    icon.sys.theme_name.clone().unwrap_or_default(),
+                    icon.sys.pixmaps.clone(),
+                ),
+                None => (String::new(), vec![]),
+            };
+
+            // Description is interpreted as markup by the tray
+            let description = if tooltip.markup {
+                tooltip.body.clone()
+            } else {
+                escape_markup(&tooltip.body)
+            };
+            return Ok((icon_name, icon_pixmap, tooltip.title.clone(), description));
+        }
+        Ok((String::new(), vec![], String::new(), String::new()))
     }
 
     /// WindowId property
@@ -1,72 +1,198 @@
-use crate::{Error, IconBase};
+use crate::{rgbaimage, Error, IconBase};
 
-/// Purpose of this struct is to keep hicon handle, and drop it when the struct
-/// is dropped
-#[derive(Debug, Clone)]
+/// Single ARGB image as in StatusNotifierItem pixmap `(iiay)`
+pub type KdePixmap = (i32, i32, Vec<u8>);
+
+/// Icon as ARGB pixmaps in every available size, or as icon theme name
+#[derive(Debug, Clone, Default)]
 pub struct KdeIcon {
-    pub width: u32,
-    pub height: u32,
-    pub argb_pixels: Option<Vec<u8>>,
+    /// Pixmaps sorted from the smallest to the largest, one per size
+    pub pixmaps: Vec<KdePixmap>,
+
+    /// Freedesktop icon theme name, preferred by the tray over the pixmaps
+    pub theme_name: Option<String>,
+}
+
+impl KdeIcon {
+    fn from_pixmaps(mut pixmaps: Vec<KdePixmap>) -> KdeIcon {
+        // Stable sort, for duplicate sizes the first one is kept
+        pixmaps.sort_by_key(|(w, h, _)| (*w as i64) * (*h as i64));
+        pixmaps.dedup_by_key(|(w, h, _)| (*w, *h));
+        KdeIcon {
+            pixmaps,
+            theme_name: None,
+        }
+    }
+}
+
+/// Pixmaps at least this large are used for menu icons, for HiDPI screens
+const MENU_ICON_SIZE: i32 = 32;
+
+impl KdeIcon {
+    /// Encode a single pixmap as PNG, as in dbusmenu `icon-data`
+    ///
+    /// Uses the smallest pixmap of at least the menu icon size, or the
+    /// largest one if all are smaller. Theme icons without fallback have no
+    /// pixmaps to encode.
+    pub fn to_png(&self) -> Result<Option<Vec<u8>>, Error> {
+        let pixmap = self
+            .pixmaps
+            .iter()
+            .find(|(w, h, _)| *w >= MENU_ICON_SIZE && *h >= MENU_ICON_SIZE)
+            .or(self.pixmaps.last());
+        pixmap
+            .map(|(w, h, argb)| rgbaimage::encode_png(&(*w as u32, *h as u32, argb_to_rgba(argb))))
+            .transpose()
+    }
 }
 
 impl IconBase for KdeIcon {
     fn from_buffer(
-        buffer: &'static [u8],
+        buffer: &[u8],
         _width: Option<u32>,
         _height: Option<u32>,
     ) -> Result<KdeIcon, Error> {
-        // Try to decode the ICO file to get the largest icon
-        let (decoded_width, decoded_height, argb_pixels) =
-            match ico::IconDir::read(std::io::Cursor::new(buffer)) {
-                Ok(icon_dir) => {
-                    // Get the largest icon entry
-                    if let Some(entry) = icon_dir
-                        .entries()
-                        .iter()
-                        .max_by_key(|e| e.width() as u32 * e.height() as u32)
-                    {
-                        match entry.decode() {
-                            Ok(image) => {
-                                let w = image.width();
-                                let h = image.height();
-                                let rgba = image.rgba_data();
-
-                                // Convert RGBA to ARGB
-                                let mut argb_pixmap = Vec::with_capacity(rgba.len());
-                                for chunk in rgba.chunks(4) {
-                                    if chunk.len() == 4 {
-                                        argb_pixmap.push(chunk[3]); // Alpha
-                                        argb_pixmap.push(chunk[0]); // Red
-                                        argb_pixmap.push(chunk[1]); // Green
-                                        argb_pixmap.push(chunk[2]); // Blue
-                                    }
-                                }
-
-                                (w, h, Some(argb_pixmap))
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to decode icon entry: {:?}", e);
-                                (0, 0, None)
-                            }
-                        }
-                    } else {
-                        eprintln!("No icon entries found");
-                        (0, 0, None)
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to read ICO file: {:?}", e);
-                    (0, 0, None)
-                }
-            };
+        Ok(KdeIcon::from_pixmaps(
+            rgbaimage::decode(buffer)?
+                .into_iter()
+                .map(|(w, h, rgba)| (w as i32, h as i32, rgba_to_argb(&rgba)))
+                .collect(),
+        ))
+    }
 
+    fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<KdeIcon, Error> {
         Ok(KdeIcon {
-            width: decoded_width,
-            height: decoded_height,
-            argb_pixels,
+            pixmaps: vec![(width as i32, height as i32, rgba_to_argb(rgba))],
+            theme_name: None,
+        })
+    }
+
+    fn from_sizes(icons: &[&KdeIcon]) -> Result<KdeIcon, Error> {
+        let mut icon = KdeIcon::from_pixmaps(
+            icons
+                .iter()
+                .flat_map(|icon| icon.pixmaps.iter().cloned())
+                .collect(),
+        );
+        icon.theme_name = icons.iter().find_map(|icon| icon.theme_name.clone());
+        Ok(icon)
+    }
+
+    fn from_theme_name(name: &str, fallback: Option<&KdeIcon>) -> Result<KdeIcon, Error> {
+        Ok(KdeIcon {
+            pixmaps: fallback
+                .map(|icon| icon.pixmaps.clone())
+                .unwrap_or_default(),
+            theme_name: Some(name.to_string()),
         })
     }
 }
 
+/// Convert RGBA to ARGB (network byte order) used by StatusNotifierItem
+fn rgba_to_argb(rgba: &[u8]) -> Vec<u8> {
+    let mut argb_pixmap = Vec::with_capacity(rgba.len());
+    for chunk in rgba.chunks_exact(4) {
+        argb_pixmap.push(chunk[3]); // Alpha
+        argb_pixmap.push(chunk[0]); // Red
+        argb_pixmap.push(chunk[1]); // Green
+        argb_pixmap.push(chunk[2]); // Blue
+    }
+    argb_pixmap
+}
+
+/// Convert ARGB (network byte order) back to RGBA
+fn argb_to_rgba(argb: &[u8]) -> Vec<u8> {
+    argb.chunks_exact(4)
+        .flat_map(|p| [p[1], p[2], p[3], p[0]])
+        .collect()
+}
+
 unsafe impl Send for KdeIcon {}
 unsafe impl Sync for KdeIcon {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_from_png() {
+        let png = rgbaimage::encode_png(&(2, 2, [1, 2, 3, 4].repeat(4))).unwrap();
+        let icon = KdeIcon::from_buffer(&png, None, None).unwrap();
+        assert_eq!(icon.pixmaps, vec![(2, 2, [4, 1, 2, 3].repeat(4))]);
+    }
+
+    #[test]
+    fn test_icon_to_png() {
+        let small = KdeIcon::from_rgba(16, 16, &[1, 2, 3, 4].repeat(16 * 16)).unwrap();
+        let large = KdeIcon::from_rgba(32, 32, &[5, 6, 7, 8].repeat(32 * 32)).unwrap();
+        let icon = KdeIcon::from_sizes(&[&small, &large]).unwrap();
+        let png = icon.to_png().unwrap().unwrap();
+        assert_eq!(
+            rgbaimage::decode(&png).unwrap(),
+            vec![(32, 32, [5, 6, 7, 8].repeat(32 * 32))]
+        );
+        let theme_icon = KdeIcon::from_theme_name("network-wireless", None).unwrap();
+        assert_eq!(theme_icon.to_png(), Ok(None));
+    }
+
+    #[test]
+    fn test_icon_from_ico_with_png() {
+        let rgba = [1, 2, 3, 4].repeat(16);
+        let image = ico::IconImage::from_rgba_data(4, 4, rgba);
+        let mut icon_dir = ico::IconDir::new(ico::ResourceType::Icon);
+        icon_dir.add_entry(ico::IconDirEntry::encode_as_png(&image).unwrap());
+        let mut buffer = vec![];
+        icon_dir.write(&mut buffer).unwrap();
+
+        let icon = KdeIcon::from_buffer(&buffer, None, None).unwrap();
+        assert_eq!(icon.pixmaps, vec![(4, 4, [4, 1, 2, 3].repeat(16))]);
+    }
+
+    #[test]
+    fn test_icon_all_ico_sizes() {
+        let mut icon_dir = ico::IconDir::new(ico::ResourceType::Icon);
+        for size in [32, 16, 24] {
+            let rgba = vec![255; size as usize * size as usize * 4];
+            let image = ico::IconImage::from_rgba_data(size, size, rgba);
+            icon_dir.add_entry(ico::IconDirEntry::encode(&image).unwrap());
+        }
+        let mut buffer = vec![];
+        icon_dir.write(&mut buffer).unwrap();
+
+        let icon = KdeIcon::from_buffer(&buffer, None, None).unwrap();
+        let sizes = icon
+            .pixmaps
+            .iter()
+            .map(|(w, h, _)| (*w, *h))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(16, 16), (24, 24), (32, 32)]);
+    }
+
+    #[test]
+    fn test_icon_from_sizes() {
+        let small = KdeIcon::from_rgba(1, 1, &[1, 2, 3, 4]).unwrap();
+        let large = KdeIcon::from_rgba(2, 2, &[5, 6, 7, 8].repeat(4)).unwrap();
+        let duplicate = KdeIcon::from_rgba(1, 1, &[0, 0, 0, 0]).unwrap();
+        let icon = KdeIcon::from_sizes(&[&large, &small, &duplicate]).unwrap();
+        assert_eq!(
+            icon.pixmaps,
+            vec![(1, 1, vec![4, 1, 2, 3]), (2, 2, [8, 5, 6, 7].repeat(4))]
+        );
+    }
+
+    #[test]
+    fn test_icon_from_theme_name() {
+        let fallback = KdeIcon::from_rgba(1, 1, &[1, 2, 3, 4]).unwrap();
+        let icon = KdeIcon::from_theme_name("network-wireless", Some(&fallback)).unwrap();
+        assert_eq!(icon.theme_name.as_deref(), Some("network-wireless"));
+        assert_eq!(icon.pixmaps, fallback.pixmaps);
+    }
+
+    #[test]
+    fn test_icon_from_invalid_buffer() {
+        assert!(matches!(
+            KdeIcon::from_buffer(b"not an icon", None, None),
+            Err(Error::IconLoadingFailed(_))
+        ));
+    }
+}
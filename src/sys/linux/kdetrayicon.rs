@@ -2,11 +2,13 @@ use super::kdeicon::KdeIcon;
 use super::MenuSys;
 use crate::{
     sys::dbus::{
-        get_dbus_connection, register_notifier_item_watcher_blocking, StatusNotifierEvent,
+        register_dbus_menu, register_notifier_item_watcher, spawn_watcher_monitor, status_name,
+        unregister, DbusMenu, ItemPaths, MenuUpdate, Registered, StatusNotifierEvent,
         StatusNotifierItemImpl,
     },
-    trayiconsender::TrayIconSender,
-    Error, TrayIconBase, TrayIconEvent,
+    trayiconsender::{Handler, TrayIconSender},
+    Capabilities, ClickEvent, ClickKind, Error, Orientation, Tooltip, TrayIconBase,
+    TrayIconBaseAsync, TrayIconEvent, TrayIconStatus,
 };
 use std::sync::{Arc, Mutex};
 
@@ -15,17 +17,25 @@ pub struct KdeTrayIconImpl<T>
 where
     T: TrayIconEvent,
 {
-    // connection: &'static zbus::Connection,
+    connection: zbus::Connection,
     // status_notifier_item: StatusNotifierItemImpl,
     // status_notifier_proxy: Box<StatusNotifierWatcherProxy<'static>>,
     // sender: TrayIconSender<T>,
+    paths: ItemPaths,
+    registered: Registered,
     #[allow(dead_code)]
     menu: Option<MenuSys<T>>,
     event_sender: Option<std::sync::mpsc::Sender<(i32, T)>>,
     icon_data: Arc<Mutex<KdeIcon>>,
-    tooltip_data: Arc<Mutex<String>>,
+    tooltip_data: Arc<Mutex<Tooltip>>,
     title_data: Arc<Mutex<String>>,
+    status_data: Arc<Mutex<TrayIconStatus>>,
+    attention_icon_data: Arc<Mutex<Option<KdeIcon>>>,
+    attention_movie_name: Arc<Mutex<String>>,
+    overlay_icon_data: Arc<Mutex<Option<KdeIcon>>>,
     last_xdg_activation_token: Arc<Mutex<Option<String>>>,
+    watcher_monitor_stop: Option<futures::channel::oneshot::Sender<()>>,
+    removed: bool,
     // notify_icon: WinNotifyIcon,
     // on_click: Option<T>,
     // on_double_click: Option<T>,
@@ -39,38 +49,74 @@ where
 {
     #[allow(clippy::new_ret_no_self)]
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn new(
+    pub(crate) async fn new(
         tray_icon_sender: TrayIconSender<T>,
+        connection: zbus::Connection,
+        paths: ItemPaths,
         menu: Option<MenuSys<T>>,
         icon: Option<&crate::Icon>,
-        tooltip: String,
+        tooltip: Tooltip,
         title: String,
+        status: TrayIconStatus,
+        icon_theme_path: String,
+        attention_icon: Option<&crate::Icon>,
+        attention_movie_name: String,
         // notify_icon: WinNotifyIcon,
         on_click: Option<T>,
         _on_double_click: Option<T>,
-        _on_right_click: Option<T>,
+        on_right_click: Option<T>,
+        on_middle_click: Option<T>,
+        on_click_event: Option<Handler<ClickEvent, T>>,
+        on_scroll: Option<Handler<(i32, Orientation), T>>,
     ) -> Result<KdeTrayIconImpl<T>, Error> {
-        let connection = get_dbus_connection();
         let (sender, receiver) = std::sync::mpsc::channel();
 
         // Extract icon data if available
-        let (icon_buffer, icon_width, icon_height) = if let Some(icon) = icon {
-            let buffer = icon.sys.argb_pixels.clone();
-            (buffer, icon.sys.width, icon.sys.height)
-        } else {
-            (None, 0, 0)
-        };
+        let icon = icon.map(|icon| icon.sys.clone()).unwrap_or_default();
 
-        let (_, icon_data_ref, tooltip_data_ref, title_data_ref) =
-            register_notifier_item_watcher_blocking(
-                connection,
-                sender.clone(),
-                icon_buffer,
-                icon_width,
-                icon_height,
-                tooltip,
-                title,
-            );
+        let icon_data = Arc::new(Mutex::new(icon));
+        let tooltip_data = Arc::new(Mutex::new(tooltip));
+        let title_data = Arc::new(Mutex::new(title));
+        let status_data = Arc::new(Mutex::new(status));
+        let attention_icon_data = Arc::new(Mutex::new(attention_icon.map(|i| i.sys.clone())));
+        let attention_movie_name = Arc::new(Mutex::new(attention_movie_name));
+        let overlay_icon_data = Arc::new(Mutex::new(None));
+
+        let status_notifier_item = StatusNotifierItemImpl {
+            id: String::new(),
+            menu_path: paths.menu.clone(),
+            channel_sender: sender.clone(),
+            icon_data: icon_data.clone(),
+            tooltip: tooltip_data.clone(),
+            title: title_data.clone(),
+            status: status_data.clone(),
+            icon_theme_path,
+            attention_icon_data: attention_icon_data.clone(),
+            attention_movie_name: attention_movie_name.clone(),
+            overlay_icon_data: overlay_icon_data.clone(),
+        };
+        // Steps done are undone if the registration fails halfway
+        let mut registered = Registered::default();
+        let registration = async {
+            if let Some(menu) = &menu {
+                register_dbus_menu(&connection, &paths, &mut registered, menu.clone()).await?;
+            }
+            register_notifier_item_watcher(
+                &connection,
+                &paths,
+                &mut registered,
+                status_notifier_item,
+            )
+            .await
+        };
+        let watcher_proxy = match registration.await {
+            Ok(watcher_proxy) => watcher_proxy,
+            Err(e) => {
+                let _ = unregister::<T>(&connection, &paths, &mut registered).await;
+                return Err(e);
+            }
+        };
+        let watcher_monitor_stop = spawn_watcher_monitor(watcher_proxy, paths.bus_name.clone());
 
         // Store the event_sender if menu exists
         let event_sender = menu.as_ref().and_then(|m| m.event_sender.clone());
@@ -80,35 +126,78 @@ where
         let last_xdg_activation_token = Arc::new(Mutex::new(None));
         let last_xdg_activation_token_clone = last_xdg_activation_token.clone();
         std::thread::spawn(move || {
+            // Token provided by the host just before the click, if any
+            let mut click_token: Option<String> = None;
             while let Ok(event) = receiver.recv() {
-                match event {
-                    // Handle events here, e.g., map to tray icon actions
-                    StatusNotifierEvent::Activate(_x, _y) => {
-                        if let Some(on_click) = &on_click {
-                            tray_sender.send(on_click);
+                let (kind, x, y, fixed_event) = match event {
+                    StatusNotifierEvent::Activate(x, y) => (ClickKind::Left, x, y, &on_click),
+                    StatusNotifierEvent::SecondaryActivate(x, y) => {
+                        (ClickKind::Middle, x, y, &on_middle_click)
+                    }
+                    // Requested only when there is no menu to show
+                    StatusNotifierEvent::ContextMenu(x, y) => {
+                        (ClickKind::Right, x, y, &on_right_click)
+                    }
+                    StatusNotifierEvent::Scroll(delta, orientation) => {
+                        let orientation = if orientation.eq_ignore_ascii_case("horizontal") {
+                            Orientation::Horizontal
+                        } else {
+                            Orientation::Vertical
+                        };
+                        if let Some(event) = on_scroll
+                            .as_ref()
+                            .and_then(|f| f.event((delta, orientation)))
+                        {
+                            tray_sender.send(&event);
                         }
+                        continue;
                     }
                     StatusNotifierEvent::ProvideXdgActivationToken(token) => {
                         if let Ok(mut last_token) = last_xdg_activation_token_clone.lock() {
-                            *last_token = Some(token);
+                            *last_token = Some(token.clone());
                         }
+                        click_token = Some(token);
+                        continue;
+                    }
+                };
+
+                if let Some(event) = fixed_event {
+                    tray_sender.send(event);
+                }
+                if let Some(on_click_event) = &on_click_event {
+                    let click = ClickEvent {
+                        kind,
+                        x,
+                        y,
+                        xdg_activation_token: click_token.take(),
+                    };
+                    if let Some(event) = on_click_event.event(click) {
+                        tray_sender.send(&event);
                     }
-                    _ => {}
                 }
+                click_token = None;
             }
         });
 
         Ok(KdeTrayIconImpl {
-            // connection,
+            connection,
             // status_notifier_proxy: Box::new(status_notifier_proxy),
             // status_notifier_item,
             // sender: tray_icon_sender,
+            paths,
+            registered,
             menu,
             event_sender,
-            icon_data: icon_data_ref,
-            tooltip_data: tooltip_data_ref,
-            title_data: title_data_ref,
+            icon_data,
+            tooltip_data,
+            title_data,
+            status_data,
+            attention_icon_data,
+            attention_movie_name,
+            overlay_icon_data,
             last_xdg_activation_token,
+            watcher_monitor_stop: Some(watcher_monitor_stop),
+            removed: false,
             // notify_icon,
             // on_click,
             // on_double_click,
@@ -117,72 +206,81 @@ where
     }
 }
 
-impl<T> TrayIconBase<T> for KdeTrayIconImpl<T>
+/// The blocking `TrayIconBase` methods wait for these
+impl<T> TrayIconBaseAsync<T> for KdeTrayIconImpl<T>
 where
     T: TrayIconEvent,
 {
-    fn set_icon(&mut self, kde_tray_icon: &crate::Icon) -> Result<(), Error> {
+    async fn set_icon_async(&mut self, kde_tray_icon: &crate::Icon) -> Result<(), Error> {
         // Update the shared icon data
         if let Ok(mut icon_data) = self.icon_data.lock() {
             *icon_data = kde_tray_icon.clone().sys;
         }
 
         // Emit NewIcon signal to notify the tray that the icon changed
-        let connection = get_dbus_connection();
-        futures::executor::block_on(async {
-            if let Ok(obj) = connection
-                .object_server()
-                .interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem")
-                .await
-            {
-                let emitter = obj.signal_emitter();
-                if let Err(e) = StatusNotifierItemImpl::new_icon(&emitter).await {
-                    eprintln!("Failed to emit NewIcon signal: {:?}", e);
-                }
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            if let Err(e) = StatusNotifierItemImpl::new_icon(emitter).await {
+                eprintln!("Failed to emit NewIcon signal: {:?}", e);
             }
-        });
+        }
 
         Ok(())
     }
 
-    fn set_menu(&mut self, menu: &crate::MenuBuilder<T>) -> Result<(), Error> {
-        use crate::sys::dbus::get_dbus_connection;
-
+    async fn set_menu_async(&mut self, menu: &crate::MenuBuilder<T>) -> Result<(), Error> {
         // Build the new menu
         let mut built_menu = super::build_menu(menu)?;
 
         // Reuse the existing event_sender so the event handling thread continues to work
         built_menu.event_sender = self.event_sender.clone();
 
-        // Get the connection and update the DBus menu
-        let connection = get_dbus_connection();
-
-        // Replace the menu at /MenuBar with the new menu
-        futures::executor::block_on(async {
-            // Remove old menu
-            let _ = connection
-                .object_server()
-                .remove::<crate::sys::dbus::DbusMenu<T>, _>("/MenuBar")
-                .await;
-
-            // Register new menu
-            let dbus_menu = crate::sys::dbus::DbusMenu::new(built_menu.clone());
-            let _ = connection
-                .object_server()
-                .at("/MenuBar", dbus_menu)
-                .await
-                .unwrap();
-
-            // Get the interface and emit layout_updated signal
-            if let Ok(iface) = connection
-                .object_server()
-                .interface::<_, crate::sys::dbus::DbusMenu<T>>("/MenuBar")
-                .await
-            {
+        // Update the registered menu in place, so that an open menu stays
+        // open when only properties such as check marks change
+        let connection = &self.connection;
+        match connection
+            .object_server()
+            .interface::<_, DbusMenu<T>>(self.paths.menu.as_str())
+            .await
+        {
+            Ok(iface) => {
+                let update = iface.get_mut().await.update(built_menu.clone());
                 let emitter = iface.signal_emitter();
-                let _ = crate::sys::dbus::DbusMenu::<T>::layout_updated(&emitter, 0, 0).await;
+                let _ = match update {
+                    MenuUpdate::Unchanged => Ok(()),
+                    MenuUpdate::Properties { updated, removed } => {
+                        DbusMenu::<T>::items_properties_updated(emitter, updated, removed).await
+                    }
+                    MenuUpdate::Layout(revision) => {
+                        DbusMenu::<T>::layout_updated(emitter, revision, 0).await
+                    }
+                };
             }
-        });
+
+            // Icon was built without menu
+            Err(_) => {
+                register_dbus_menu(
+                    connection,
+                    &self.paths,
+                    &mut self.registered,
+                    built_menu.clone(),
+                )
+                .await?;
+                if let Ok(iface) = connection
+                    .object_server()
+                    .interface::<_, DbusMenu<T>>(self.paths.menu.as_str())
+                    .await
+                {
+                    let emitter = iface.signal_emitter();
+                    let _ = DbusMenu::<T>::layout_updated(emitter, 0, 0).await;
+                }
+            }
+        }
 
         // Store the new menu
         self.menu = Some(built_menu);
@@ -190,75 +288,194 @@ where
         Ok(())
     }
 
-    fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Error> {
+    async fn set_tooltip_async(&mut self, tooltip: &Tooltip) -> Result<(), Error> {
         // Update the tooltip data
         if let Ok(mut tooltip_data) = self.tooltip_data.lock() {
-            *tooltip_data = tooltip.to_string();
+            *tooltip_data = tooltip.clone();
         }
 
         // Emit the NewToolTip signal to notify the system tray
-        let connection = get_dbus_connection();
-        futures::executor::block_on(async {
-            if let Ok(obj) = connection
-                .object_server()
-                .interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem")
-                .await
-            {
-                let emitter = obj.signal_emitter();
-                let _ = StatusNotifierItemImpl::new_tool_tip(&emitter).await;
-            }
-        });
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            let _ = StatusNotifierItemImpl::new_tool_tip(emitter).await;
+        }
 
         Ok(())
     }
 
-    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+    async fn set_title_async(&mut self, title: &str) -> Result<(), Error> {
         // Update the title data
         if let Ok(mut title_data) = self.title_data.lock() {
             *title_data = title.to_string();
         }
 
         // Emit the NewTitle signal to notify the system tray
-        let connection = get_dbus_connection();
-        futures::executor::block_on(async {
-            if let Ok(obj) = connection
-                .object_server()
-                .interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem")
-                .await
-            {
-                let emitter = obj.signal_emitter();
-                let _ = StatusNotifierItemImpl::new_title(&emitter).await;
-            }
-        });
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            let _ = StatusNotifierItemImpl::new_title(emitter).await;
+        }
 
         Ok(())
     }
 
-    fn set_status(&mut self, status: crate::TrayIconStatus) -> Result<(), Error> {
-        use crate::TrayIconStatus;
+    async fn set_status_async(&mut self, status: TrayIconStatus) -> Result<(), Error> {
+        // Update the status data, it's read back by the Status property
+        if let Ok(mut status_data) = self.status_data.lock() {
+            *status_data = status;
+        }
+        let status_str = status_name(status);
+
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            if let Err(e) = StatusNotifierItemImpl::new_status(emitter, status_str).await {
+                eprintln!("Failed to emit NewStatus signal: {:?}", e);
+            }
+        }
+        Ok(())
+    }
 
-        let status_str = match status {
-            TrayIconStatus::Active => "Active",
-            TrayIconStatus::NeedsAttention => "NeedsAttention",
-            TrayIconStatus::Passive => "Passive",
+    async fn set_attention_icon_async(
+        &mut self,
+        icon: Option<&crate::Icon>,
+        movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        if let Ok(mut attention_icon_data) = self.attention_icon_data.lock() {
+            *attention_icon_data = icon.map(|i| i.sys.clone());
+        }
+        if let Ok(mut attention_movie_name) = self.attention_movie_name.lock() {
+            *attention_movie_name = movie_name.unwrap_or_default().to_string();
+        }
+
+        // Emit the NewAttentionIcon signal to notify the system tray
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            let _ = StatusNotifierItemImpl::new_attention_icon(emitter).await;
+        }
+
+        Ok(())
+    }
+
+    async fn set_overlay_icon_async(
+        &mut self,
+        icon: &crate::Icon,
+        overlay: Option<&crate::Icon>,
+    ) -> Result<(), Error> {
+        // The tray draws the overlay, the icon itself is kept as is
+        let icon_changed = if let Ok(mut icon_data) = self.icon_data.lock() {
+            let changed = icon_data.pixmaps != icon.sys.pixmaps
+                || icon_data.theme_name != icon.sys.theme_name;
+            *icon_data = icon.sys.clone();
+            changed
+        } else {
+            false
         };
+        if let Ok(mut overlay_icon_data) = self.overlay_icon_data.lock() {
+            *overlay_icon_data = overlay.map(|i| i.sys.clone());
+        }
 
-        let connection = get_dbus_connection();
-        futures::executor::block_on(async {
-            if let Ok(obj) = connection
-                .object_server()
-                .interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem")
-                .await
-            {
-                let emitter = obj.signal_emitter();
-                if let Err(e) = StatusNotifierItemImpl::new_status(&emitter, status_str).await {
-                    eprintln!("Failed to emit NewStatus signal: {:?}", e);
-                }
+        // Emit the NewIcon and NewOverlayIcon signals to notify the system tray
+        let connection = &self.connection;
+        if let Ok(obj) = connection
+            .object_server()
+            .interface::<_, StatusNotifierItemImpl>(self.paths.item.as_str())
+            .await
+        {
+            let emitter = obj.signal_emitter();
+            if icon_changed {
+                let _ = StatusNotifierItemImpl::new_icon(emitter).await;
             }
-        });
+            let _ = StatusNotifierItemImpl::new_overlay_icon(emitter).await;
+        }
+
         Ok(())
     }
 
+    async fn remove_async(&mut self) -> Result<(), Error> {
+        if !self.stop() {
+            return Ok(());
+        }
+        unregister::<T>(&self.connection, &self.paths, &mut self.registered).await
+    }
+}
+
+impl<T> TrayIconBase<T> for KdeTrayIconImpl<T>
+where
+    T: TrayIconEvent,
+{
+    fn set_icon(&mut self, icon: &crate::Icon) -> Result<(), Error> {
+        futures::executor::block_on(self.set_icon_async(icon))
+    }
+
+    fn set_menu(&mut self, menu: &crate::MenuBuilder<T>) -> Result<(), Error> {
+        futures::executor::block_on(self.set_menu_async(menu))
+    }
+
+    fn set_tooltip(&mut self, tooltip: &Tooltip) -> Result<(), Error> {
+        futures::executor::block_on(self.set_tooltip_async(tooltip))
+    }
+
+    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        futures::executor::block_on(self.set_title_async(title))
+    }
+
+    fn set_status(&mut self, status: TrayIconStatus) -> Result<(), Error> {
+        futures::executor::block_on(self.set_status_async(status))
+    }
+
+    fn set_attention_icon(
+        &mut self,
+        icon: Option<&crate::Icon>,
+        movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        futures::executor::block_on(self.set_attention_icon_async(icon, movie_name))
+    }
+
+    fn set_overlay_icon(
+        &mut self,
+        icon: &crate::Icon,
+        overlay: Option<&crate::Icon>,
+    ) -> Result<(), Error> {
+        futures::executor::block_on(self.set_overlay_icon_async(icon, overlay))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            click: true,
+            right_click: true,
+            middle_click: true,
+            click_event: true,
+            scroll: true,
+            title: true,
+            status: true,
+            attention_icon: true,
+            native_overlay_icon: true,
+            rich_tooltip: true,
+            theme_icons: true,
+            xdg_activation_token: true,
+            ..Default::default()
+        }
+    }
+
     fn get_xdg_activation_token(&self) -> Option<String> {
         if let Ok(token_lock) = self.last_xdg_activation_token.lock() {
             token_lock.clone()
@@ -266,4 +483,58 @@ where
             None
         }
     }
+
+    fn remove(&mut self) -> Result<(), Error> {
+        futures::executor::block_on(self.remove_async())
+    }
+}
+
+impl<T> KdeTrayIconImpl<T>
+where
+    T: TrayIconEvent,
+{
+    /// Stop the watcher monitor and drop the menu, returns false if the icon
+    /// was removed already
+    ///
+    /// Releasing the bus name removes the item from the watcher. Dropping the
+    /// objects and the menu drops the event senders, which ends the event
+    /// threads.
+    fn stop(&mut self) -> bool {
+        if self.removed {
+            return false;
+        }
+        self.removed = true;
+        if let Some(stop) = self.watcher_monitor_stop.take() {
+            let _ = stop.send(());
+        }
+        self.menu = None;
+        self.event_sender = None;
+        true
+    }
+}
+
+impl<T> Drop for KdeTrayIconImpl<T>
+where
+    T: TrayIconEvent,
+{
+    fn drop(&mut self) {
+        if !self.stop() {
+            return;
+        }
+
+        // Blocking here would deadlock when dropped inside an async runtime,
+        // the executor of the connection unregisters the item instead
+        let connection = self.connection.clone();
+        let paths = self.paths.clone();
+        let mut registered = self.registered;
+        self.connection
+            .executor()
+            .spawn(
+                async move {
+                    let _ = unregister::<T>(&connection, &paths, &mut registered).await;
+                },
+                "trayicon unregister",
+            )
+            .detach();
+    }
 }
@@ -1,4 +1,7 @@
-use crate::{Error, MenuBuilder, MenuItem, TrayIconBuilder, TrayIconEvent};
+use crate::{
+    shortcut::mnemonic_to_underscore, Error, MenuBuilder, MenuItem, Shortcut, TrayIconBuilder,
+    TrayIconEvent,
+};
 
 mod dbus;
 mod kdeicon;
@@ -14,8 +17,15 @@ pub struct MenuItemData<T: TrayIconEvent> {
     pub event_id: Option<T>,
     pub is_separator: bool,
     pub is_checkable: bool,
+    pub is_radio: bool,
     pub is_checked: bool,
     pub is_disabled: bool,
+    pub is_hidden: bool,
+    /// Theme icon name, as in dbusmenu `icon-name`
+    pub icon_name: Option<String>,
+    /// PNG file, as in dbusmenu `icon-data`
+    pub icon_data: Option<Vec<u8>>,
+    pub shortcut: Option<Shortcut>,
     pub children: Vec<MenuItemData<T>>,
 }
 
@@ -40,8 +50,37 @@ where
     }
 }
 
+/// Linux specific options of the tray icon builder
+pub trait TrayIconBuilderExt {
+    /// Serve the tray icon on the given connection
+    ///
+    /// By default each tray icon opens its own session bus connection. This is
+    /// for applications owning a connection already, or for using a private
+    /// bus e.g. in tests. Only one tray icon can be served per connection, the
+    /// item is at `/StatusNotifierItem` as trays expect.
+    fn dbus_connection(self, connection: zbus::Connection) -> Self;
+}
+
+impl<T> TrayIconBuilderExt for TrayIconBuilder<T>
+where
+    T: TrayIconEvent,
+{
+    fn dbus_connection(mut self, connection: zbus::Connection) -> Self {
+        self.dbus_connection = Some(connection);
+        self
+    }
+}
+
 /// Build the tray icon
 pub fn build_trayicon<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
+where
+    T: TrayIconEvent,
+{
+    futures::executor::block_on(build_trayicon_async(builder))
+}
+
+/// Build the tray icon without blocking
+pub async fn build_trayicon_async<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
 where
     T: TrayIconEvent,
 {
@@ -51,12 +90,23 @@ where
         .title
         .clone()
         .unwrap_or_else(|| "Application".to_string());
+    let icon_theme_path = builder.icon_theme_path.clone().unwrap_or_default();
+    let attention_icon = builder.attention_icon.as_ref();
+    let attention_movie_name = builder.attention_movie_name.clone().unwrap_or_default();
     let icon = builder.icon.as_ref()?;
     let on_click = builder.on_click.clone();
     let on_right_click = builder.on_right_click.clone();
     let sender = builder.sender.clone().ok_or(Error::SenderMissing)?;
     let on_double_click = builder.on_double_click.clone();
+    let on_middle_click = builder.on_middle_click.clone();
+    let on_click_event = builder.on_click_event.clone();
+    let on_scroll = builder.on_scroll.clone();
     // let notify_icon = WinNotifyIcon::new(hicon, tooltip);
+    let connection = match &builder.dbus_connection {
+        Some(connection) => connection.clone(),
+        None => open_dbus_connection().await?,
+    };
+    let paths = ItemPaths::next();
 
     // Try to get a popup menu
     if let Some(rhmenu) = &builder.menu {
@@ -76,24 +126,30 @@ where
             }
         });
 
-        // Register the menu with DBus
-        let connection = get_dbus_connection();
-        register_dbus_menu_blocking(connection, built_menu.clone());
-
         menu = Some(built_menu);
     }
 
-    Ok(TrayIconSys::new(
+    TrayIconSys::new(
         sender,
+        connection,
+        paths,
         menu,
         Some(icon),
         tooltip,
         title,
+        builder.status,
+        icon_theme_path,
+        attention_icon,
+        attention_movie_name,
         // notify_icon,
         on_click,
         on_double_click,
         on_right_click,
-    )?)
+        on_middle_click,
+        on_click_event,
+        on_scroll,
+    )
+    .await
 }
 
 /// Build the menu from Windows HMENU
@@ -129,6 +185,18 @@ where
 {
     *j += 1;
     let current_id = *j as i32;
+    let icon = match item {
+        MenuItem::Item { icon, .. }
+        | MenuItem::Checkable { icon, .. }
+        | MenuItem::Radio { icon, .. }
+        | MenuItem::Submenu { icon, .. } => icon.as_ref(),
+        MenuItem::Separator => None,
+    };
+    let icon_name = icon.and_then(|icon| icon.sys.theme_name.clone());
+    let icon_data = match icon {
+        Some(icon) => icon.sys.to_png()?,
+        None => None,
+    };
 
     match item {
         MenuItem::Separator => Ok(MenuItemData {
@@ -137,20 +205,35 @@ where
             event_id: None,
             is_separator: true,
             is_checkable: false,
+            is_radio: false,
             is_checked: false,
             is_disabled: false,
+            is_hidden: false,
+            icon_name: None,
+            icon_data: None,
+            shortcut: None,
             children: vec![],
         }),
         MenuItem::Item {
-            id, name, disabled, ..
+            id,
+            name,
+            disabled,
+            hidden,
+            shortcut,
+            ..
         } => Ok(MenuItemData {
             id: current_id,
-            label: name.clone(),
+            label: mnemonic_to_underscore(name),
             event_id: Some(id.clone()),
             is_separator: false,
             is_checkable: false,
+            is_radio: false,
             is_checked: false,
             is_disabled: *disabled,
+            is_hidden: *hidden,
+            icon_name,
+            icon_data,
+            shortcut: shortcut.clone(),
             children: vec![],
         }),
         MenuItem::Checkable {
@@ -158,21 +241,38 @@ where
             name,
             is_checked,
             disabled,
+            hidden,
+            shortcut,
+            ..
+        }
+        | MenuItem::Radio {
+            id,
+            name,
+            is_checked,
+            disabled,
+            hidden,
+            shortcut,
             ..
         } => Ok(MenuItemData {
             id: current_id,
-            label: name.clone(),
+            label: mnemonic_to_underscore(name),
             event_id: Some(id.clone()),
             is_separator: false,
             is_checkable: true,
+            is_radio: matches!(item, MenuItem::Radio { .. }),
             is_checked: *is_checked,
             is_disabled: *disabled,
+            is_hidden: *hidden,
+            icon_name,
+            icon_data,
+            shortcut: shortcut.clone(),
             children: vec![],
         }),
         MenuItem::Submenu {
             name,
             children,
             disabled,
+            hidden,
             ..
         } => {
             let mut child_items = vec![];
@@ -182,12 +282,17 @@ where
             }
             Ok(MenuItemData {
                 id: current_id,
-                label: name.clone(),
+                label: mnemonic_to_underscore(name),
                 event_id: None,
                 is_separator: false,
                 is_checkable: false,
+                is_radio: false,
                 is_checked: false,
                 is_disabled: *disabled,
+                is_hidden: *hidden,
+                icon_name,
+                icon_data,
+                shortcut: None,
                 children: child_items,
             })
         }
@@ -197,6 +302,10 @@ where
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
+    use crate::{Icon, TrayIconBuilder};
+    use std::io::BufRead;
+    use std::sync::{Arc, Mutex};
+    use zbus::proxy::CacheProperties;
 
     #[derive(Copy, Clone, Eq, PartialEq, Debug)]
     enum Events {
@@ -256,4 +365,244 @@ pub(crate) mod tests {
             panic!()
         }
     }
+
+    /// Bus daemon of a single test, killed on drop
+    struct PrivateBus {
+        daemon: std::process::Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// Start `dbus-daemon`, `None` if it's not installed
+        fn start() -> Option<PrivateBus> {
+            let mut daemon = std::process::Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            let stdout = daemon.stdout.take()?;
+            std::io::BufReader::new(stdout)
+                .read_line(&mut address)
+                .ok()?;
+            Some(PrivateBus {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> zbus::connection::Builder<'static> {
+            zbus::connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Watcher keeping the registered bus names while they have an owner
+    struct FakeWatcher {
+        items: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
+    impl FakeWatcher {
+        fn register_status_notifier_item(&self, service: &str) {
+            self.items.lock().unwrap().push(service.to_string());
+        }
+
+        #[zbus(property)]
+        async fn registered_status_notifier_items(
+            &self,
+            #[zbus(connection)] connection: &zbus::Connection,
+        ) -> Vec<String> {
+            let dbus_proxy = zbus::fdo::DBusProxy::new(connection).await.unwrap();
+            let items = self.items.lock().unwrap().clone();
+            let mut registered = vec![];
+            for item in items {
+                let name = item.as_str().try_into().unwrap();
+                if dbus_proxy.name_has_owner(name).await.unwrap() {
+                    registered.push(item);
+                }
+            }
+            registered
+        }
+    }
+
+    #[test]
+    fn test_remove_unregisters_from_watcher() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        };
+        let items = Arc::new(Mutex::new(vec![]));
+        let (_watcher, connection, watcher_proxy) = futures::executor::block_on(async {
+            let watcher = bus
+                .connect()
+                .name("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .serve_at(
+                    "/StatusNotifierWatcher",
+                    FakeWatcher {
+                        items: items.clone(),
+                    },
+                )
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let connection = bus.connect().build().await.unwrap();
+            let watcher_proxy = StatusNotifierWatcherProxy::builder(&connection)
+                .destination("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .path("/StatusNotifierWatcher")
+                .unwrap()
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+            (watcher, connection, watcher_proxy)
+        });
+        let registered = || {
+            futures::executor::block_on(watcher_proxy.registered_status_notifier_items()).unwrap()
+        };
+
+        let tray_icon = TrayIconBuilder::<Events>::new()
+            .sender(|_| {})
+            .icon(Icon::from_rgba(1, 1, vec![255; 4]).unwrap())
+            .menu(MenuBuilder::new().item("Item 1", Events::Item1))
+            .dbus_connection(connection.clone())
+            .build()
+            .unwrap();
+        assert_eq!(registered().len(), 1);
+
+        // Connection is still open, as it's owned by the test
+        tray_icon.remove().unwrap();
+        assert_eq!(registered(), Vec::<String>::new());
+        assert_eq!(items.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_drop_in_async_context_unregisters() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        };
+        let (_watcher, connection, watcher_proxy) = futures::executor::block_on(async {
+            let watcher = bus
+                .connect()
+                .name("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .serve_at(
+                    "/StatusNotifierWatcher",
+                    FakeWatcher {
+                        items: Arc::new(Mutex::new(vec![])),
+                    },
+                )
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let connection = bus.connect().build().await.unwrap();
+            let watcher_proxy = StatusNotifierWatcherProxy::builder(&connection)
+                .destination("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .path("/StatusNotifierWatcher")
+                .unwrap()
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+            (watcher, connection, watcher_proxy)
+        });
+        let registered = || {
+            futures::executor::block_on(watcher_proxy.registered_status_notifier_items()).unwrap()
+        };
+
+        let tray_icon = TrayIconBuilder::<Events>::new()
+            .sender(|_| {})
+            .icon(Icon::from_rgba(1, 1, vec![255; 4]).unwrap())
+            .dbus_connection(connection.clone())
+            .build()
+            .unwrap();
+        assert_eq!(registered().len(), 1);
+
+        // Dropping must not block on the executor that runs the drop
+        futures::executor::block_on(async move { drop(tray_icon) });
+        for _ in 0..100 {
+            if registered().is_empty() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("item is still registered after the drop");
+    }
+
+    #[test]
+    fn test_second_icon_on_connection_keeps_first() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        };
+        let (_watcher, connection, watcher_proxy) = futures::executor::block_on(async {
+            let watcher = bus
+                .connect()
+                .name("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .serve_at(
+                    "/StatusNotifierWatcher",
+                    FakeWatcher {
+                        items: Arc::new(Mutex::new(vec![])),
+                    },
+                )
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let connection = bus.connect().build().await.unwrap();
+            let watcher_proxy = StatusNotifierWatcherProxy::builder(&connection)
+                .destination("org.kde.StatusNotifierWatcher")
+                .unwrap()
+                .path("/StatusNotifierWatcher")
+                .unwrap()
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+            (watcher, connection, watcher_proxy)
+        });
+        let registered = || {
+            futures::executor::block_on(watcher_proxy.registered_status_notifier_items()).unwrap()
+        };
+        let build = || {
+            TrayIconBuilder::<Events>::new()
+                .sender(|_| {})
+                .icon(Icon::from_rgba(1, 1, vec![255; 4]).unwrap())
+                .menu(MenuBuilder::new().item("Item 1", Events::Item1))
+                .dbus_connection(connection.clone())
+                .build()
+        };
+
+        let tray_icon = build().unwrap();
+        let first = registered();
+        assert_eq!(first.len(), 1);
+
+        // Paths are served by the first icon, they must stay that way
+        assert!(build().is_err());
+        assert_eq!(registered(), first);
+        let object_server = connection.object_server();
+        futures::executor::block_on(
+            object_server.interface::<_, StatusNotifierItemImpl>("/StatusNotifierItem"),
+        )
+        .unwrap();
+        futures::executor::block_on(object_server.interface::<_, DbusMenu<Events>>("/MenuBar"))
+            .unwrap();
+
+        tray_icon.remove().unwrap();
+        assert_eq!(registered(), Vec::<String>::new());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{Error, IconBase};
 use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
 use objc2::{msg_send, AnyThread};
 use objc2_app_kit::NSImage;
 use objc2_foundation::{NSData, NSSize};
@@ -11,12 +12,14 @@ pub struct MacIcon {
 
 impl IconBase for MacIcon {
     fn from_buffer(
-        buffer: &'static [u8],
+        buffer: &[u8],
         width: Option<u32>,
         height: Option<u32>,
     ) -> Result<MacIcon, Error> {
         let ns_data = NSData::with_bytes(buffer);
-        let ns_image = NSImage::initWithData(NSImage::alloc(), &ns_data).ok_or(Error::OsError)?;
+        let ns_image = NSImage::initWithData(NSImage::alloc(), &ns_data).ok_or_else(|| {
+            Error::IconLoadingFailed("NSImage could not decode the image data".to_string())
+        })?;
 
         // Set size if provided
         if let (Some(w), Some(h)) = (width, height) {
@@ -28,6 +31,79 @@ impl IconBase for MacIcon {
 
         Ok(MacIcon { ns_image })
     }
+
+    fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<MacIcon, Error> {
+        MacIcon::from_buffer(&rgba_to_ico(width, height, rgba), None, None)
+    }
+
+    fn from_sizes(icons: &[&MacIcon]) -> Result<MacIcon, Error> {
+        let first = icons
+            .first()
+            .ok_or_else(|| Error::IconLoadingFailed("no icon sizes given".to_string()))?;
+
+        // Size in points is taken from the first icon, AppKit picks the best
+        // representation for the screen when drawing
+        let ns_image: Retained<NSImage> = unsafe {
+            let size: NSSize = msg_send![&first.ns_image, size];
+            msg_send![NSImage::alloc(), initWithSize: size]
+        };
+        for icon in icons {
+            unsafe {
+                let reps: *mut AnyObject = msg_send![&icon.ns_image, representations];
+                let count: usize = msg_send![reps, count];
+                for i in 0..count {
+                    let rep: *mut AnyObject = msg_send![reps, objectAtIndex: i];
+                    let _: () = msg_send![&ns_image, addRepresentation: rep];
+                }
+            }
+        }
+
+        Ok(MacIcon { ns_image })
+    }
+}
+
+/// Wraps RGBA pixels into a single image ICO file with 32-bit DIB
+///
+/// NSImage reads ICO files natively, this avoids dealing with the bitmap image
+/// representation formats directly.
+fn rgba_to_ico(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mask_row = (width as usize).div_ceil(32) * 4;
+    let image_size = 40 + rgba.len() + mask_row * height as usize;
+    let mut ico = Vec::with_capacity(6 + 16 + image_size);
+
+    // ICONDIR
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+
+    // ICONDIRENTRY, sizes of 256 and above are stored as zero
+    ico.push(if width >= 256 { 0 } else { width as u8 });
+    ico.push(if height >= 256 { 0 } else { height as u8 });
+    ico.push(0);
+    ico.push(0);
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&32u16.to_le_bytes());
+    ico.extend_from_slice(&(image_size as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+
+    // BITMAPINFOHEADER, height covers both the color and the mask bitmap
+    ico.extend_from_slice(&40u32.to_le_bytes());
+    ico.extend_from_slice(&(width as i32).to_le_bytes());
+    ico.extend_from_slice(&(height as i32 * 2).to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&32u16.to_le_bytes());
+    ico.extend_from_slice(&[0u8; 24]);
+
+    // BGRA pixels, bottom-up
+    for row in rgba.chunks_exact(width as usize * 4).rev() {
+        for pixel in row.chunks_exact(4) {
+            ico.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+
+    // AND mask, transparency comes from the alpha channel
+    ico.resize(ico.len() + mask_row * height as usize, 0);
+    ico
 }
 
 impl MacIcon {
@@ -1,4 +1,7 @@
-use crate::{trayiconsender::TrayIconSender, Error, MenuBuilder, MenuItem, TrayIconEvent};
+use crate::{
+    shortcut::strip_mnemonic, trayiconsender::TrayIconSender, Error, MenuBuilder, MenuItem,
+    Modifier, Shortcut, TrayIconEvent,
+};
 use objc2::rc::{Allocated, Retained};
 use objc2::runtime::Sel;
 use objc2::{class, define_class, msg_send, DeclaredClass, MainThreadOnly};
@@ -119,7 +122,8 @@ where
                 name,
                 children,
                 disabled,
-                ..
+                hidden,
+                icon,
             } => {
                 if let Some(id) = id {
                     *j += 1;
@@ -129,7 +133,7 @@ where
                 if let Ok(submenu_sys) = build_menu_inner(j, children, target, menu_ids) {
                     map.extend(submenu_sys.ids.into_iter());
 
-                    let ns_title = NSString::from_str(name);
+                    let ns_title = NSString::from_str(&strip_mnemonic(name));
                     let empty_str = NSString::new();
                     let menu_item = unsafe {
                         let allocated: Allocated<NSMenuItem> = msg_send![class!(NSMenuItem), alloc];
@@ -144,6 +148,10 @@ where
                     unsafe {
                         menu_item.setSubmenu(Some(&submenu_sys.menu));
                         menu_item.setEnabled(!disabled);
+                        menu_item.setHidden(*hidden);
+                        if let Some(icon) = icon {
+                            menu_item.setImage(Some(&icon.sys.ns_image));
+                        }
                         menu.addItem(&menu_item);
                     }
                 }
@@ -154,20 +162,33 @@ where
                 is_checked,
                 id,
                 disabled,
+                hidden,
+                icon,
+                shortcut,
+                ..
+            }
+            | MenuItem::Radio {
+                name,
+                is_checked,
+                id,
+                disabled,
+                hidden,
+                icon,
+                shortcut,
                 ..
             } => {
                 *j += 1;
                 map.insert(*j, id.clone());
 
-                let ns_title = NSString::from_str(name);
-                let empty_str = NSString::new();
+                let ns_title = NSString::from_str(&strip_mnemonic(name));
+                let (key_equivalent, modifier_mask) = shortcut_key_equivalent(shortcut);
                 let menu_item = unsafe {
                     let allocated: Allocated<NSMenuItem> = msg_send![class!(NSMenuItem), alloc];
                     let action_sel = Sel::register(c"menuItemClicked:");
                     let menu_item: Retained<NSMenuItem> = msg_send![allocated,
                         initWithTitle: &*ns_title,
                         action: Some(action_sel),
-                        keyEquivalent: &*empty_str
+                        keyEquivalent: &*key_equivalent
                     ];
                     menu_item
                 };
@@ -175,7 +196,12 @@ where
                 unsafe {
                     menu_item.setTag(*j as isize);
                     menu_item.setTarget(Some(target));
+                    let _: () = msg_send![&menu_item, setKeyEquivalentModifierMask: modifier_mask];
                     menu_item.setEnabled(!disabled);
+                    menu_item.setHidden(*hidden);
+                    if let Some(icon) = icon {
+                        menu_item.setImage(Some(&icon.sys.ns_image));
+                    }
                     let _: () = msg_send![&menu_item, setState: if *is_checked { 1_isize } else { 0_isize }];
                     menu.addItem(&menu_item);
                 }
@@ -188,20 +214,25 @@ where
             }
 
             MenuItem::Item {
-                name, id, disabled, ..
+                name,
+                id,
+                disabled,
+                hidden,
+                icon,
+                shortcut,
             } => {
                 *j += 1;
                 map.insert(*j, id.clone());
 
-                let ns_title = NSString::from_str(name);
-                let empty_str = NSString::new();
+                let ns_title = NSString::from_str(&strip_mnemonic(name));
+                let (key_equivalent, modifier_mask) = shortcut_key_equivalent(shortcut);
                 let menu_item = unsafe {
                     let allocated: Allocated<NSMenuItem> = msg_send![class!(NSMenuItem), alloc];
                     let action_sel = Sel::register(c"menuItemClicked:");
                     let menu_item: Retained<NSMenuItem> = msg_send![allocated,
                         initWithTitle: &*ns_title,
                         action: Some(action_sel),
-                        keyEquivalent: &*empty_str
+                        keyEquivalent: &*key_equivalent
                     ];
                     menu_item
                 };
@@ -209,7 +240,12 @@ where
                 unsafe {
                     menu_item.setTag(*j as isize);
                     menu_item.setTarget(Some(target));
+                    let _: () = msg_send![&menu_item, setKeyEquivalentModifierMask: modifier_mask];
                     menu_item.setEnabled(!disabled);
+                    menu_item.setHidden(*hidden);
+                    if let Some(icon) = icon {
+                        menu_item.setImage(Some(&icon.sys.ns_image));
+                    }
                     menu.addItem(&menu_item);
                 }
 
@@ -264,3 +300,30 @@ impl<T: TrayIconEvent> MacMenu<T> {
         }
     }
 }
+
+/// Key equivalent and modifier mask of the shortcut
+///
+/// Only single character keys are supported, Control is mapped to Command.
+fn shortcut_key_equivalent(shortcut: &Option<Shortcut>) -> (Retained<NSString>, usize) {
+    // NSEventModifierFlags
+    const SHIFT: usize = 1 << 17;
+    const CONTROL: usize = 1 << 18;
+    const OPTION: usize = 1 << 19;
+    const COMMAND: usize = 1 << 20;
+
+    match shortcut {
+        Some(shortcut) if shortcut.key.chars().count() == 1 => {
+            let mask = shortcut
+                .modifiers
+                .iter()
+                .fold(0, |mask, modifier| match modifier {
+                    Modifier::Control => mask | COMMAND,
+                    Modifier::Alt => mask | OPTION,
+                    Modifier::Shift => mask | SHIFT,
+                    Modifier::Super => mask | CONTROL,
+                });
+            (NSString::from_str(&shortcut.key.to_lowercase()), mask)
+        }
+        _ => (NSString::new(), 0),
+    }
+}
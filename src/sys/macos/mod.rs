@@ -20,6 +20,15 @@ where
     menu: menu::MacMenu<T>,
 }
 
+/// Build the tray icon, creating the status item doesn't block
+#[cfg(feature = "async")]
+pub async fn build_trayicon_async<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
+where
+    T: TrayIconEvent,
+{
+    trayicon::build_trayicon(builder)
+}
+
 /// Build the tray icon
 pub fn build_trayicon<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
 where
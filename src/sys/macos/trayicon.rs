@@ -1,7 +1,7 @@
 use super::menu::{build_menu, MacMenu};
 use crate::{
-    trayiconsender::TrayIconSender, Error, Icon, MenuBuilder, TrayIconBase, TrayIconBuilder,
-    TrayIconEvent,
+    trayiconsender::TrayIconSender, Capabilities, Error, Icon, MenuBuilder, Tooltip, TrayIconBase,
+    TrayIconBuilder, TrayIconEvent,
 };
 use objc2::msg_send;
 use objc2::rc::Retained;
@@ -51,17 +51,22 @@ where
         Ok(())
     }
 
-    fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Error> {
+    fn set_tooltip(&mut self, tooltip: &Tooltip) -> Result<(), Error> {
         unsafe {
             let button: *mut AnyObject = msg_send![&self.status_item, button];
             if !button.is_null() {
-                let ns_tooltip = NSString::from_str(tooltip);
+                let ns_tooltip = NSString::from_str(&tooltip.to_plain_text());
                 let _: () = msg_send![button, setToolTip: &*ns_tooltip];
             }
         }
         Ok(())
     }
 
+    fn capabilities(&self) -> Capabilities {
+        // Clicking opens the menu, click events are not implemented
+        Capabilities::default()
+    }
+
     fn show_menu(&mut self) -> Result<(), Error> {
         if let Some(ref menu_sys) = self.menu {
             unsafe {
@@ -72,6 +77,9 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+impl<T> crate::TrayIconBaseAsync<T> for MacTrayIcon<T> where T: TrayIconEvent {}
+
 impl<T> Drop for MacTrayIcon<T>
 where
     T: TrayIconEvent,
@@ -93,7 +101,11 @@ where
     T: TrayIconEvent,
 {
     let icon = builder.icon.as_ref()?;
-    let tooltip = builder.tooltip.as_deref().unwrap_or("");
+    let tooltip = builder
+        .tooltip
+        .as_ref()
+        .map(|t| t.to_plain_text())
+        .unwrap_or_default();
     let sender = builder.sender.as_ref().ok_or(Error::SenderMissing)?;
     let on_click = builder.on_click.clone();
     let on_double_click = builder.on_double_click.clone();
@@ -113,7 +125,7 @@ where
         let button: *mut AnyObject = msg_send![&status_item, button];
         if !button.is_null() {
             let _: () = msg_send![button, setImage: &*icon.sys.ns_image];
-            let ns_tooltip = NSString::from_str(tooltip);
+            let ns_tooltip = NSString::from_str(&tooltip);
             let _: () = msg_send![button, setToolTip: &*ns_tooltip];
         }
 
@@ -7,7 +7,7 @@ mod wintrayicon;
 use std::collections::HashMap;
 use wintrayicon::WinTrayIconImpl;
 
-use crate::{Error, MenuBuilder, MenuItem, TrayIconBuilder, TrayIconEvent};
+use crate::{Error, MenuBuilder, MenuItem, Shortcut, TrayIconBuilder, TrayIconEvent};
 use winhmenu::WinHMenu;
 use winnotifyicon::WinNotifyIcon;
 
@@ -24,19 +24,30 @@ where
     menu: WinHMenu,
 }
 
+/// Build the tray icon, creating the window doesn't block
+#[cfg(feature = "async")]
+pub async fn build_trayicon_async<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
+where
+    T: TrayIconEvent,
+{
+    build_trayicon(builder)
+}
+
 /// Build the tray icon
 pub fn build_trayicon<T>(builder: &TrayIconBuilder<T>) -> Result<TrayIconSys<T>, Error>
 where
     T: TrayIconEvent,
 {
     let mut menu: Option<MenuSys<T>> = None;
-    let tooltip = &builder.tooltip;
+    let tooltip = builder.tooltip.as_ref().map(|t| t.to_plain_text());
     let hicon = &builder.icon.as_ref()?.sys;
     let on_click = builder.on_click.clone();
     let on_right_click = builder.on_right_click.clone();
     let sender = builder.sender.clone().ok_or(Error::SenderMissing)?;
     let on_double_click = builder.on_double_click.clone();
-    let notify_icon = WinNotifyIcon::new(hicon, tooltip);
+    let on_middle_click = builder.on_middle_click.clone();
+    let on_click_event = builder.on_click_event.clone();
+    let notify_icon = WinNotifyIcon::new(hicon, &tooltip);
 
     // Try to get a popup menu
     if let Some(rhmenu) = &builder.menu {
@@ -50,6 +61,8 @@ where
         on_click,
         on_double_click,
         on_right_click,
+        on_middle_click,
+        on_click_event,
     )?)
 }
 
@@ -73,6 +86,12 @@ where
     let mut hmenu = WinHMenu::new()?;
     let mut map: HashMap<usize, T> = HashMap::new();
     builder.menu_items.iter().for_each(|item| match item {
+        // Hidden items are left out, the menu is built again to show them
+        MenuItem::Item { hidden: true, .. }
+        | MenuItem::Checkable { hidden: true, .. }
+        | MenuItem::Radio { hidden: true, .. }
+        | MenuItem::Submenu { hidden: true, .. } => {}
+
         MenuItem::Submenu {
             id,
             name,
@@ -95,19 +114,37 @@ where
             is_checked,
             id,
             disabled,
+            shortcut,
+            ..
+        } => {
+            *j += 1;
+            map.insert(*j, id.clone());
+            hmenu.add_checkable_item(&label(name, shortcut), *is_checked, *j, *disabled);
+        }
+
+        MenuItem::Radio {
+            name,
+            is_checked,
+            id,
+            disabled,
+            shortcut,
             ..
         } => {
             *j += 1;
             map.insert(*j, id.clone());
-            hmenu.add_checkable_item(&name, *is_checked, *j, *disabled);
+            hmenu.add_radio_item(&label(name, shortcut), *is_checked, *j, *disabled);
         }
 
         MenuItem::Item {
-            name, id, disabled, ..
+            name,
+            id,
+            disabled,
+            shortcut,
+            ..
         } => {
             *j += 1;
             map.insert(*j, id.clone());
-            hmenu.add_menu_item(&name, *j, *disabled);
+            hmenu.add_menu_item(&label(name, shortcut), *j, *disabled);
         }
 
         MenuItem::Separator => {
@@ -121,6 +158,14 @@ where
     })
 }
 
+/// Menu item label with the shortcut right aligned after a tab
+fn label(name: &str, shortcut: &Option<Shortcut>) -> String {
+    match shortcut {
+        Some(shortcut) => format!("{}\t{}", name, shortcut),
+        None => name.to_string(),
+    }
+}
+
 // For pattern matching, these are in own mod
 mod msgs {
     pub const WM_USER_TRAYICON: u32 = 0x400 + 1001;
@@ -179,4 +224,25 @@ pub(crate) mod tests {
             panic!()
         }
     }
+
+    #[test]
+    fn test_menu_build_hidden() {
+        let mut builder = MenuBuilder::new()
+            .item("Item 1", Events::Item1)
+            .checkable("This is checkable", true, Events::CheckableItem1)
+            .submenu(
+                "Sub Menu",
+                MenuBuilder::new()
+                    .item("Sub item 1", Events::SubItem1)
+                    .item("Sub Item 2", Events::SubItem2),
+            );
+        builder.set_hidden(Events::CheckableItem1, true).unwrap();
+        builder.set_hidden(Events::SubItem2, true).unwrap();
+
+        // Hidden items are left out of the menu
+        let menusys = build_menu(&builder).unwrap();
+        let mut ids = menusys.ids.values().copied().collect::<Vec<_>>();
+        ids.sort_by_key(|id| *id as u32);
+        assert_eq!(ids, vec![Events::Item1, Events::SubItem1]);
+    }
 }
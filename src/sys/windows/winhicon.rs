@@ -1,7 +1,7 @@
-use crate::{Error, IconBase};
+use crate::{icon::IconFormat, Error, IconBase};
 use winapi::shared::minwindef::PBYTE;
 use winapi::shared::windef::HICON;
-use winapi::um::winuser;
+use winapi::um::{wingdi, winuser};
 
 /// Purpose of this struct is to keep hicon handle, and drop it when the struct
 /// is dropped
@@ -11,23 +11,31 @@ pub struct WinHIcon {
 
 impl IconBase for WinHIcon {
     fn from_buffer(
-        buffer: &'static [u8],
+        buffer: &[u8],
         width: Option<u32>,
         height: Option<u32>,
     ) -> Result<WinHIcon, Error> {
-        let offset = unsafe {
-            winuser::LookupIconIdFromDirectoryEx(
-                buffer.as_ptr() as PBYTE,
-                1,
-                width.unwrap_or_default() as i32,
-                height.unwrap_or_default() as i32,
-                winuser::LR_DEFAULTCOLOR,
-            )
+        let icon_data = match IconFormat::sniff(buffer)? {
+            // PNG files are accepted as is by CreateIconFromResourceEx
+            IconFormat::Png => buffer,
+            IconFormat::Ico => {
+                let offset = unsafe {
+                    winuser::LookupIconIdFromDirectoryEx(
+                        buffer.as_ptr() as PBYTE,
+                        1,
+                        width.unwrap_or_default() as i32,
+                        height.unwrap_or_default() as i32,
+                        winuser::LR_DEFAULTCOLOR,
+                    )
+                };
+                if offset <= 0 {
+                    return Err(Error::IconLoadingFailed(
+                        "no suitable image found in ICO file".to_string(),
+                    ));
+                }
+                &buffer[offset as usize..]
+            }
         };
-        if offset <= 0 {
-            return Err(Error::IconLoadingFailed);
-        }
-        let icon_data = &buffer[offset as usize..];
         let hicon = unsafe {
             winuser::CreateIconFromResourceEx(
                 icon_data.as_ptr() as PBYTE,
@@ -40,10 +48,83 @@ impl IconBase for WinHIcon {
             )
         };
         if hicon.is_null() {
-            return Err(Error::IconLoadingFailed);
+            return Err(Error::IconLoadingFailed(
+                "CreateIconFromResourceEx failed".to_string(),
+            ));
+        }
+        Ok(WinHIcon { hicon })
+    }
+
+    fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<WinHIcon, Error> {
+        // Windows expects BGRA pixels
+        let mut bgra = rgba.to_vec();
+        for pixel in bgra.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+
+        // Transparency comes from the alpha channel, AND mask rows are WORD
+        // aligned and left empty
+        let and_mask = vec![0u8; (width as usize).div_ceil(16) * 2 * height as usize];
+
+        let hicon = unsafe {
+            winuser::CreateIcon(
+                std::ptr::null_mut(),
+                width as i32,
+                height as i32,
+                1,
+                32,
+                and_mask.as_ptr(),
+                bgra.as_ptr(),
+            )
+        };
+        if hicon.is_null() {
+            return Err(Error::IconLoadingFailed("CreateIcon failed".to_string()));
         }
         Ok(WinHIcon { hicon })
     }
+
+    fn from_sizes(icons: &[&WinHIcon]) -> Result<WinHIcon, Error> {
+        let small_icon_width = unsafe { winuser::GetSystemMetrics(winuser::SM_CXSMICON) };
+        icons
+            .iter()
+            .min_by_key(|icon| (icon.width() - small_icon_width).abs())
+            .map(|icon| (*icon).clone())
+            .ok_or_else(|| Error::IconLoadingFailed("no icon sizes given".to_string()))
+    }
+}
+
+impl WinHIcon {
+    /// Width of the icon bitmap in pixels
+    fn width(&self) -> i32 {
+        unsafe {
+            let mut info: winuser::ICONINFO = std::mem::zeroed();
+            if winuser::GetIconInfo(self.hicon, &mut info) == 0 {
+                return 0;
+            }
+
+            // Monochrome icons have only the mask bitmap
+            let hbitmap = if info.hbmColor.is_null() {
+                info.hbmMask
+            } else {
+                info.hbmColor
+            };
+            let mut bitmap: wingdi::BITMAP = std::mem::zeroed();
+            wingdi::GetObjectW(
+                hbitmap as _,
+                std::mem::size_of::<wingdi::BITMAP>() as i32,
+                &mut bitmap as *mut _ as _,
+            );
+
+            // GetIconInfo creates copies of the bitmaps
+            if !info.hbmColor.is_null() {
+                wingdi::DeleteObject(info.hbmColor as _);
+            }
+            if !info.hbmMask.is_null() {
+                wingdi::DeleteObject(info.hbmMask as _);
+            }
+            bitmap.bmWidth
+        }
+    }
 }
 
 impl Clone for WinHIcon {
@@ -18,7 +18,10 @@ impl WinHMenu {
             hmenu: unsafe {
                 let res = winuser::CreatePopupMenu();
                 if res.is_null() {
-                    return Err(Error::OsError);
+                    return Err(Error::os_with_source(
+                        "CreatePopupMenu failed",
+                        std::io::Error::last_os_error(),
+                    ));
                 }
                 res
             },
@@ -63,6 +66,21 @@ impl WinHMenu {
         let res = unsafe { winuser::AppendMenuW(self.hmenu, flags, id, wchar(name).as_ptr() as _) };
         res >= 0
     }
+
+    pub fn add_radio_item(&self, name: &str, is_checked: bool, id: usize, disabled: bool) -> bool {
+        if !self.add_checkable_item(name, is_checked, id, disabled) {
+            return false;
+        }
+
+        // AppendMenuW can't set the radio check mark, it's set afterwards
+        let mut info: winuser::MENUITEMINFOW = unsafe { std::mem::zeroed() };
+        info.cbSize = std::mem::size_of::<winuser::MENUITEMINFOW>() as u32;
+        info.fMask = winuser::MIIM_FTYPE;
+        info.fType = winuser::MFT_STRING | winuser::MFT_RADIOCHECK;
+        let res = unsafe { winuser::SetMenuItemInfoW(self.hmenu, id as u32, 0, &info) };
+        res != 0
+    }
+
     pub fn add_child_menu(&mut self, name: &str, menu: WinHMenu, disabled: bool) -> bool {
         let mut flags = winuser::MF_POPUP;
         if disabled {
@@ -10,7 +10,9 @@ use winapi::um::winuser;
 use super::wchar::wchar;
 use super::{msgs, winnotifyicon::WinNotifyIcon, MenuSys};
 use crate::{
-    trayiconsender::TrayIconSender, Error, Icon, MenuBuilder, TrayIconBase, TrayIconEvent,
+    trayiconsender::{Handler, TrayIconSender},
+    Capabilities, ClickEvent, ClickKind, Error, Icon, MenuBuilder, Tooltip, TrayIconBase,
+    TrayIconEvent,
 };
 
 pub type WinTrayIcon<T> = WindowBox<T>;
@@ -70,6 +72,8 @@ where
     on_click: Option<T>,
     on_double_click: Option<T>,
     on_right_click: Option<T>,
+    on_middle_click: Option<T>,
+    on_click_event: Option<Handler<ClickEvent, T>>,
     msg_taskbarcreated: Option<UINT>,
 }
 
@@ -89,6 +93,8 @@ where
         on_click: Option<T>,
         on_double_click: Option<T>,
         on_right_click: Option<T>,
+        on_middle_click: Option<T>,
+        on_click_event: Option<Handler<ClickEvent, T>>,
     ) -> Result<WinTrayIcon<T>, Error>
     where
         T: PartialEq + Clone + 'static,
@@ -118,6 +124,8 @@ where
                 on_click,
                 on_right_click,
                 on_double_click,
+                on_middle_click,
+                on_click_event,
                 sender,
                 msg_taskbarcreated: None,
             });
@@ -138,7 +146,10 @@ where
             ) as u32;
 
             if hwnd == 0 {
-                return Err(Error::OsError);
+                return Err(Error::os_with_source(
+                    "CreateWindowExW failed",
+                    std::io::Error::last_os_error(),
+                ));
             }
 
             Ok(WindowBox(ptr))
@@ -172,20 +183,44 @@ where
 
             // Mouse events on the tray icon
             msgs::WM_USER_TRAYICON => {
+                // Click event with the cursor position from on_click_event
+                let click_event = |kind| {
+                    let on_click_event = self.on_click_event.as_ref()?;
+                    let mut pos = POINT { x: 0, y: 0 };
+                    unsafe {
+                        winuser::GetCursorPos(&mut pos as _);
+                    }
+                    on_click_event.event(ClickEvent {
+                        kind,
+                        x: pos.x,
+                        y: pos.y,
+                        xdg_activation_token: None,
+                    })
+                };
+
                 match lparam as u32 {
                     // Left click tray icon
                     winuser::WM_LBUTTONUP => {
                         if let Some(e) = self.on_click.as_ref() {
                             self.sender.send(e);
                         }
+                        if let Some(e) = click_event(ClickKind::Left) {
+                            self.sender.send(&e);
+                        }
                     }
 
                     // Right click tray icon
                     winuser::WM_RBUTTONUP => {
-                        // Send right click event or show menu if on_right_click is None
+                        let click = click_event(ClickKind::Right);
+                        if let Some(e) = &click {
+                            self.sender.send(e);
+                        }
+
+                        // Send right click event or show menu if neither
+                        // on_right_click or on_click_event handles it
                         if let Some(e) = self.on_right_click.as_ref() {
                             self.sender.send(e);
-                        } else {
+                        } else if click.is_none() {
                             // Default behavior: show menu on right click
                             if let Some(menu) = &self.menu {
                                 let mut pos = POINT { x: 0, y: 0 };
@@ -198,11 +233,24 @@ where
                         }
                     }
 
+                    // Middle click tray icon
+                    winuser::WM_MBUTTONUP => {
+                        if let Some(e) = self.on_middle_click.as_ref() {
+                            self.sender.send(e);
+                        }
+                        if let Some(e) = click_event(ClickKind::Middle) {
+                            self.sender.send(&e);
+                        }
+                    }
+
                     // Double click tray icon
                     winuser::WM_LBUTTONDBLCLK => {
                         if let Some(e) = self.on_double_click.as_ref() {
                             self.sender.send(e);
                         }
+                        if let Some(e) = click_event(ClickKind::Double) {
+                            self.sender.send(&e);
+                        }
                     }
                     _ => {}
                 }
@@ -282,10 +330,22 @@ impl<T> TrayIconBase<T> for WinTrayIconImpl<T>
 where
     T: TrayIconEvent,
 {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            click: true,
+            double_click: true,
+            right_click: true,
+            middle_click: true,
+            click_event: true,
+            show_menu: true,
+            ..Default::default()
+        }
+    }
+
     /// Set the tooltip
-    fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Error> {
-        if !self.notify_icon.set_tooltip(tooltip) {
-            return Err(Error::OsError);
+    fn set_tooltip(&mut self, tooltip: &Tooltip) -> Result<(), Error> {
+        if !self.notify_icon.set_tooltip(&tooltip.to_plain_text()) {
+            return Err(Error::os("Shell_NotifyIconW failed to set the tooltip"));
         }
         Ok(())
     }
@@ -293,7 +353,9 @@ where
     /// Set icon
     fn set_icon(&mut self, icon: &Icon) -> Result<(), Error> {
         if !self.notify_icon.set_icon(&icon.sys) {
-            return Err(Error::IconLoadingFailed);
+            return Err(Error::IconLoadingFailed(
+                "Shell_NotifyIcon failed to set the icon".to_string(),
+            ));
         }
         Ok(())
     }
@@ -319,6 +381,9 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+impl<T> crate::TrayIconBaseAsync<T> for WinTrayIconImpl<T> where T: TrayIconEvent {}
+
 impl<T> Drop for WinTrayIconImpl<T>
 where
     T: TrayIconEvent,
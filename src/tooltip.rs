@@ -0,0 +1,108 @@
+use crate::Icon;
+
+/// Tooltip with title, body and icon
+///
+/// KDE shows all the parts, body may contain markup (subset of HTML) if
+/// `markup` is set. On other platforms the tooltip is plain text with title
+/// and body on separate lines.
+///
+/// Plain strings convert to a tooltip with only body.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tooltip {
+    pub title: String,
+    pub body: String,
+    pub icon: Option<Icon>,
+    pub markup: bool,
+}
+
+impl Tooltip {
+    pub fn new(title: &str, body: &str) -> Tooltip {
+        Tooltip {
+            title: title.to_string(),
+            body: body.to_string(),
+            icon: None,
+            markup: false,
+        }
+    }
+
+    /// Set icon (KDE only)
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Set whether body contains markup
+    pub fn markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Title and body on separate lines, markup tags removed
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub(crate) fn to_plain_text(&self) -> String {
+        let body = if self.markup {
+            strip_markup(&self.body)
+        } else {
+            self.body.clone()
+        };
+        match (self.title.is_empty(), body.is_empty()) {
+            (true, _) => body,
+            (false, true) => self.title.clone(),
+            (false, false) => format!("{}\n{}", self.title, body),
+        }
+    }
+}
+
+impl From<&str> for Tooltip {
+    fn from(body: &str) -> Self {
+        Tooltip::new("", body)
+    }
+}
+
+impl From<String> for Tooltip {
+    fn from(body: String) -> Self {
+        Tooltip {
+            body,
+            ..Default::default()
+        }
+    }
+}
+
+/// Remove tags and decode the basic entities
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn strip_markup(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tooltip_plain_text() {
+        assert_eq!(Tooltip::from("Body").to_plain_text(), "Body");
+        assert_eq!(Tooltip::new("Title", "").to_plain_text(), "Title");
+        assert_eq!(Tooltip::new("Title", "Body").to_plain_text(), "Title\nBody");
+        assert_eq!(
+            Tooltip::new("Title", "<b>Bold</b> &amp; <i>italic</i>")
+                .markup(true)
+                .to_plain_text(),
+            "Title\nBold & italic"
+        );
+        assert_eq!(Tooltip::from("1 < 2").to_plain_text(), "1 < 2");
+    }
+}
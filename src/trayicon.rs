@@ -1,7 +1,11 @@
 use crate::{
-    Error, Icon, MenuBuilder, TrayIconBase, TrayIconBuilder, TrayIconEvent, TrayIconStatus,
+    Capabilities, Error, Icon, MenuBuilder, Tooltip, TrayIconBase, TrayIconBuilder, TrayIconEvent,
+    TrayIconStatus,
 };
 
+#[cfg(feature = "async")]
+use crate::TrayIconBaseAsync;
+
 pub struct TrayIcon<T>
 where
     T: TrayIconEvent,
@@ -18,13 +22,39 @@ where
         TrayIcon { builder, sys }
     }
 
+    /// Features supported by the tray of the platform
+    ///
+    /// Methods of unsupported features do nothing, e.g. `set_title` on
+    /// Windows. Use this for adapting the application instead.
+    pub fn capabilities(&self) -> Capabilities {
+        self.sys.capabilities()
+    }
+
     /// Set the icon if changed
     pub fn set_icon(&mut self, icon: &Icon) -> Result<(), Error> {
-        if self.builder.icon.as_ref() == Ok(icon) {
+        if !self.builder.update_icon(icon) {
+            return Ok(());
+        }
+        match &self.builder.overlay_icon {
+            Some(overlay) => self.sys.set_overlay_icon(icon, Some(overlay)),
+            None => self.sys.set_icon(icon),
+        }
+    }
+
+    /// Set the overlay icon if changed
+    ///
+    /// Overlay is a small badge shown on top of the icon, e.g. for showing a
+    /// connection state. On KDE the tray draws the overlay, on other platforms
+    /// it's drawn on the bottom right corner of the icon. Give `None` to remove
+    /// the overlay.
+    pub fn set_overlay_icon(&mut self, overlay: Option<Icon>) -> Result<(), Error> {
+        if !self.builder.update_overlay_icon(overlay)? {
             return Ok(());
         }
-        self.builder.icon = Ok(icon.clone());
-        self.sys.set_icon(icon)
+        self.sys.set_overlay_icon(
+            self.builder.icon.as_ref()?,
+            self.builder.overlay_icon.as_ref(),
+        )
     }
 
     /// Set the menu if changed
@@ -34,20 +64,21 @@ where
     /// using more imperative `set_item_checkable`, `get_item_checkable` and
     /// `set_item_disabled` methods.
     pub fn set_menu(&mut self, menu: &MenuBuilder<T>) -> Result<(), Error> {
-        if self.builder.menu.as_ref() == Some(menu) {
+        if !self.builder.update_menu(menu)? {
             return Ok(());
         }
-        self.builder.menu = Some(menu.clone());
         self.sys.set_menu(menu)
     }
 
     /// Set the tooltip if changed
-    pub fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Error> {
-        if self.builder.tooltip.as_deref() == Some(tooltip) {
+    ///
+    /// Accepts plain strings or `Tooltip` with title, body and icon.
+    pub fn set_tooltip(&mut self, tooltip: impl Into<Tooltip>) -> Result<(), Error> {
+        let tooltip = tooltip.into();
+        if !self.builder.update_tooltip(&tooltip) {
             return Ok(());
         }
-        self.builder.tooltip = Some(tooltip.to_string());
-        self.sys.set_tooltip(tooltip)
+        self.sys.set_tooltip(&tooltip)
     }
 
     /// Set the title (KDE only)
@@ -55,10 +86,9 @@ where
     /// Used in KDE as the application title for the tray icon (Title property).
     /// On other platforms, this does nothing.
     pub fn set_title(&mut self, title: &str) -> Result<(), Error> {
-        if self.builder.title.as_deref() == Some(title) {
+        if !self.builder.update_title(title) {
             return Ok(());
         }
-        self.builder.title = Some(title.to_string());
         self.sys.set_title(title)
     }
 
@@ -75,8 +105,26 @@ where
         Ok(())
     }
 
+    /// Set visible
+    ///
+    /// Hidden items stay in the menu, so showing them again doesn't need a new
+    /// menu.
+    ///
+    /// Prefer building a new menu if application state changes instead of
+    /// mutating a menu with this method. Suggestion is to use just `set_menu`
+    /// method instead of this.
+    pub fn set_menu_item_visible(&mut self, id: T, visible: bool) -> Result<(), Error> {
+        if let Some(menu) = self.builder.menu.as_mut() {
+            let _ = menu.set_hidden(id, !visible);
+            let _ = self.sys.set_menu(menu);
+        }
+        Ok(())
+    }
+
     /// Set checkable
     ///
+    /// Works also for radio items, checking one unchecks the rest of its group.
+    ///
     /// Prefer building a new menu when application state changes instead of
     /// mutating a menu with this method.  Suggestion is to use just `set_menu`
     /// method instead of this.
@@ -108,7 +156,7 @@ where
         self.sys.show_menu()
     }
 
-    /// Set the status of the tray icon (KDE only)
+    /// Set the status of the tray icon if changed (KDE only)
     ///
     /// On KDE, this controls the StatusNotifierItem status:
     /// - Active: Normal visible state
@@ -117,15 +165,278 @@ where
     ///
     /// On other platforms, this does nothing by default.
     pub fn set_status(&mut self, status: TrayIconStatus) -> Result<(), Error> {
+        if !self.builder.update_status(status) {
+            return Ok(());
+        }
         self.sys.set_status(status)
     }
 
+    /// Get the status of the tray icon
+    pub fn status(&self) -> TrayIconStatus {
+        self.builder.status
+    }
+
+    /// Set the attention icon and animation if changed (KDE only)
+    ///
+    /// Shown by the tray instead of the icon when status is
+    /// `TrayIconStatus::NeedsAttention`. The animation is a theme name or a
+    /// path, trays not supporting animations show the attention icon.
+    ///
+    /// On other platforms, this does nothing.
+    pub fn set_attention_icon(
+        &mut self,
+        icon: Option<&Icon>,
+        movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        if !self.builder.update_attention_icon(icon, movie_name) {
+            return Ok(());
+        }
+        self.sys.set_attention_icon(icon, movie_name)
+    }
+
     /// Get the XDG activation token (KDE only)
     pub fn get_xdg_activation_token(&self) -> Option<String> {
         self.sys.get_xdg_activation_token()
     }
+
+    /// Remove the icon from the tray
+    ///
+    /// Dropping the tray icon removes it as well, this is for handling the
+    /// errors of the removal.
+    pub fn remove(mut self) -> Result<(), Error> {
+        self.sys.remove()
+    }
+}
+
+/// Async versions of the setters
+///
+/// On Linux the blocking setters wait for D-Bus with `block_on`, which can't
+/// be done inside an async runtime. On other platforms these don't block in
+/// the first place and simply call the blocking setters.
+#[cfg(feature = "async")]
+impl<T> TrayIcon<T>
+where
+    T: TrayIconEvent,
+{
+    /// Set the icon if changed
+    pub async fn set_icon_async(&mut self, icon: &Icon) -> Result<(), Error> {
+        if !self.builder.update_icon(icon) {
+            return Ok(());
+        }
+        match &self.builder.overlay_icon {
+            Some(overlay) => self.sys.set_overlay_icon_async(icon, Some(overlay)).await,
+            None => self.sys.set_icon_async(icon).await,
+        }
+    }
+
+    /// Set the overlay icon if changed, see `set_overlay_icon`
+    pub async fn set_overlay_icon_async(&mut self, overlay: Option<Icon>) -> Result<(), Error> {
+        if !self.builder.update_overlay_icon(overlay)? {
+            return Ok(());
+        }
+        self.sys
+            .set_overlay_icon_async(
+                self.builder.icon.as_ref()?,
+                self.builder.overlay_icon.as_ref(),
+            )
+            .await
+    }
+
+    /// Set the menu if changed
+    pub async fn set_menu_async(&mut self, menu: &MenuBuilder<T>) -> Result<(), Error> {
+        if !self.builder.update_menu(menu)? {
+            return Ok(());
+        }
+        self.sys.set_menu_async(menu).await
+    }
+
+    /// Set the tooltip if changed
+    pub async fn set_tooltip_async(&mut self, tooltip: impl Into<Tooltip>) -> Result<(), Error> {
+        let tooltip = tooltip.into();
+        if !self.builder.update_tooltip(&tooltip) {
+            return Ok(());
+        }
+        self.sys.set_tooltip_async(&tooltip).await
+    }
+
+    /// Set the title if changed (KDE only)
+    pub async fn set_title_async(&mut self, title: &str) -> Result<(), Error> {
+        if !self.builder.update_title(title) {
+            return Ok(());
+        }
+        self.sys.set_title_async(title).await
+    }
+
+    /// Set the status if changed (KDE only), see `set_status`
+    pub async fn set_status_async(&mut self, status: TrayIconStatus) -> Result<(), Error> {
+        if !self.builder.update_status(status) {
+            return Ok(());
+        }
+        self.sys.set_status_async(status).await
+    }
+
+    /// Set the attention icon and animation if changed (KDE only)
+    pub async fn set_attention_icon_async(
+        &mut self,
+        icon: Option<&Icon>,
+        movie_name: Option<&str>,
+    ) -> Result<(), Error> {
+        if !self.builder.update_attention_icon(icon, movie_name) {
+            return Ok(());
+        }
+        self.sys.set_attention_icon_async(icon, movie_name).await
+    }
+
+    /// Set disabled, see `set_menu_item_disabled`
+    pub async fn set_menu_item_disabled_async(
+        &mut self,
+        id: T,
+        disabled: bool,
+    ) -> Result<(), Error> {
+        if let Some(menu) = self.builder.menu.as_mut() {
+            let _ = menu.set_disabled(id, disabled);
+            let _ = self.sys.set_menu_async(menu).await;
+        }
+        Ok(())
+    }
+
+    /// Set visible, see `set_menu_item_visible`
+    pub async fn set_menu_item_visible_async(&mut self, id: T, visible: bool) -> Result<(), Error> {
+        if let Some(menu) = self.builder.menu.as_mut() {
+            let _ = menu.set_hidden(id, !visible);
+            let _ = self.sys.set_menu_async(menu).await;
+        }
+        Ok(())
+    }
+
+    /// Set checkable, see `set_menu_item_checkable`
+    pub async fn set_menu_item_checkable_async(
+        &mut self,
+        id: T,
+        checked: bool,
+    ) -> Result<(), Error> {
+        if let Some(menu) = self.builder.menu.as_mut() {
+            let _ = menu.set_checkable(id, checked);
+            let _ = self.sys.set_menu_async(menu).await;
+        }
+        Ok(())
+    }
+
+    /// Remove the icon from the tray
+    ///
+    /// Use this instead of dropping the icon in async code, on Linux dropping
+    /// waits for the removal with `block_on`.
+    pub async fn remove_async(mut self) -> Result<(), Error> {
+        self.sys.remove_async().await
+    }
+}
+
+/// State of the tray icon is kept in its builder, the setters of both the
+/// blocking and the async API store the new values with these and call the
+/// platform only if something changed.
+impl<T> TrayIconBuilder<T>
+where
+    T: TrayIconEvent,
+{
+    /// Store the icon, false if unchanged
+    fn update_icon(&mut self, icon: &Icon) -> bool {
+        if self.icon.as_ref() == Ok(icon) {
+            return false;
+        }
+        self.icon = Ok(icon.clone());
+        true
+    }
+
+    /// Store the overlay icon, false if unchanged
+    ///
+    /// Overlay is not stored if there is no icon to show it on.
+    fn update_overlay_icon(&mut self, overlay: Option<Icon>) -> Result<bool, Error> {
+        if self.overlay_icon == overlay {
+            return Ok(false);
+        }
+        self.icon.as_ref()?;
+        self.overlay_icon = overlay;
+        Ok(true)
+    }
+
+    /// Store the menu, false if unchanged
+    fn update_menu(&mut self, menu: &MenuBuilder<T>) -> Result<bool, Error> {
+        if self.menu.as_ref() == Some(menu) {
+            return Ok(false);
+        }
+        menu.check_unique_ids()?;
+        self.menu = Some(menu.clone());
+        Ok(true)
+    }
+
+    /// Store the tooltip, false if unchanged
+    fn update_tooltip(&mut self, tooltip: &Tooltip) -> bool {
+        if self.tooltip.as_ref() == Some(tooltip) {
+            return false;
+        }
+        self.tooltip = Some(tooltip.clone());
+        true
+    }
+
+    /// Store the title, false if unchanged
+    fn update_title(&mut self, title: &str) -> bool {
+        if self.title.as_deref() == Some(title) {
+            return false;
+        }
+        self.title = Some(title.to_string());
+        true
+    }
+
+    /// Store the status, false if unchanged
+    fn update_status(&mut self, status: TrayIconStatus) -> bool {
+        if self.status == status {
+            return false;
+        }
+        self.status = status;
+        true
+    }
+
+    /// Store the attention icon and animation, false if unchanged
+    fn update_attention_icon(&mut self, icon: Option<&Icon>, movie_name: Option<&str>) -> bool {
+        if self.attention_icon.as_ref() == icon
+            && self.attention_movie_name.as_deref() == movie_name
+        {
+            return false;
+        }
+        self.attention_icon = icon.cloned();
+        self.attention_movie_name = movie_name.map(|name| name.to_string());
+        true
+    }
 }
 
 unsafe impl<T> Sync for TrayIcon<T> where T: TrayIconEvent {}
 
 unsafe impl<T> Send for TrayIcon<T> where T: TrayIconEvent {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_update() {
+        let icon = Icon::from_rgba(1, 1, vec![1, 2, 3, 4]).unwrap();
+        let overlay = Icon::from_rgba(1, 1, vec![5, 6, 7, 8]).unwrap();
+        let mut builder = TrayIconBuilder::<u32>::new();
+
+        // Overlay is not stored without an icon
+        assert_eq!(
+            builder.update_overlay_icon(Some(overlay.clone())),
+            Err(Error::IconMissing)
+        );
+        assert_eq!(builder.overlay_icon, None);
+
+        assert!(builder.update_icon(&icon));
+        assert!(!builder.update_icon(&icon));
+        assert_eq!(builder.update_overlay_icon(Some(overlay.clone())), Ok(true));
+        assert_eq!(builder.update_overlay_icon(Some(overlay)), Ok(false));
+        assert!(builder.update_attention_icon(None, Some("mail-unread")));
+        assert!(!builder.update_attention_icon(None, Some("mail-unread")));
+        assert!(builder.update_title("Title"));
+        assert!(!builder.update_title("Title"));
+    }
+}
@@ -1,29 +1,9 @@
-use crate::{trayiconsender::TrayIconSender, Icon, MenuBuilder, TrayIcon, TrayIconEvent};
-use std::fmt::{Display, Formatter};
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Error {
-    MenuItemNotFound,
-    IconLoadingFailed,
-    SenderMissing,
-    IconMissing,
-    OsError,
-}
-
-// Why do I need to do this, can't Rust do this automatically?
-impl From<&Error> for Error {
-    fn from(e: &Error) -> Self {
-        *e
-    }
-}
-
-impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
-    }
-}
-
-impl std::error::Error for Error {}
+use crate::{
+    trayiconsender::{Handler, TrayIconSender},
+    ClickEvent, Error, Icon, MenuBuilder, Orientation, Tooltip, TrayIcon, TrayIconEvent,
+    TrayIconStatus,
+};
+use std::sync::mpsc::Receiver;
 
 /// Tray Icon builder
 ///
@@ -36,11 +16,25 @@ where
     pub(crate) icon: Result<Icon, Error>,
     pub(crate) menu: Option<MenuBuilder<T>>,
     pub(crate) title: Option<String>,
-    pub(crate) tooltip: Option<String>,
+    pub(crate) tooltip: Option<Tooltip>,
+    pub(crate) status: TrayIconStatus,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) icon_theme_path: Option<String>,
+    pub(crate) attention_icon: Option<Icon>,
+    pub(crate) attention_movie_name: Option<String>,
+    pub(crate) overlay_icon: Option<Icon>,
     pub(crate) on_click: Option<T>,
     pub(crate) on_double_click: Option<T>,
     pub(crate) on_right_click: Option<T>,
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) on_middle_click: Option<T>,
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) on_click_event: Option<Handler<ClickEvent, T>>,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) on_scroll: Option<Handler<(i32, Orientation), T>>,
     pub(crate) sender: Option<TrayIconSender<T>>,
+    #[cfg(target_os = "linux")]
+    pub(crate) dbus_connection: Option<zbus::Connection>,
 }
 
 impl<T> TrayIconBuilder<T>
@@ -54,10 +48,20 @@ where
             menu: None,
             title: None,
             tooltip: None,
+            status: TrayIconStatus::Active,
+            icon_theme_path: None,
+            attention_icon: None,
+            attention_movie_name: None,
+            overlay_icon: None,
             on_click: None,
             on_double_click: None,
             on_right_click: None,
+            on_middle_click: None,
+            on_click_event: None,
+            on_scroll: None,
             sender: None,
+            #[cfg(target_os = "linux")]
+            dbus_connection: None,
         }
     }
 
@@ -69,6 +73,10 @@ where
         f(self)
     }
 
+    /// Set the callback receiving the events
+    ///
+    /// Required by `build`, see `build_with_receiver` for receiving the events
+    /// from a channel instead.
     pub fn sender(mut self, cb: impl Fn(&T) + Send + Sync + 'static) -> Self {
         self.sender = Some(TrayIconSender::new(cb));
         self
@@ -82,8 +90,19 @@ where
         self
     }
 
-    pub fn tooltip(mut self, tooltip: &str) -> Self {
-        self.tooltip = Some(tooltip.to_string());
+    /// Set tooltip
+    ///
+    /// Accepts plain strings or `Tooltip` with title, body and icon.
+    pub fn tooltip(mut self, tooltip: impl Into<Tooltip>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Set initial status (KDE only)
+    ///
+    /// Defaults to `TrayIconStatus::Active`, see `TrayIcon::set_status`.
+    pub fn status(mut self, status: TrayIconStatus) -> Self {
+        self.status = status;
         self
     }
 
@@ -105,7 +124,7 @@ where
 
     /// Set right click event handler
     ///
-    /// Binding works only on Windows. In KDE and MacOS right click by default opens the menu, binding this is not effective.
+    /// Binding works on Windows, and on KDE when the icon has no menu. In KDE with a menu and in MacOS right click opens the menu, binding this is not effective.
     ///
     /// If not given in Windows it will default to showing the menu on right click like in KDE and MacOS.
     pub fn on_right_click(mut self, id: T) -> Self {
@@ -113,6 +132,48 @@ where
         self
     }
 
+    /// Set middle click event handler
+    ///
+    /// Works only on Windows and KDE, in KDE this is the secondary activation
+    /// of the item.
+    pub fn on_middle_click(mut self, id: T) -> Self {
+        self.on_middle_click = Some(id);
+        self
+    }
+
+    /// Set click event handler with the click position
+    ///
+    /// Called for every click with the mouse button and screen position, e.g.
+    /// for placing own popup window next to the icon. Returned event is sent
+    /// to the sender in addition to the events of `on_click` and others.
+    /// Return `None` to ignore the click.
+    ///
+    /// Works only on Windows and KDE. In KDE right click is received only
+    /// without menu, and double click is not registered. If this returns an
+    /// event for right click in Windows the menu is not shown.
+    pub fn on_click_event(
+        mut self,
+        f: impl Fn(ClickEvent) -> Option<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_click_event = Some(Handler::new(f));
+        self
+    }
+
+    /// Set scroll event handler (KDE only)
+    ///
+    /// Called with the scroll delta and orientation when scrolling over the
+    /// icon, returned event is sent to the sender. Return `None` to ignore the
+    /// scroll.
+    pub fn on_scroll(
+        mut self,
+        f: impl Fn(i32, Orientation) -> Option<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_scroll = Some(Handler::new(move |(delta, orientation)| {
+            f(delta, orientation)
+        }));
+        self
+    }
+
     pub fn icon(mut self, icon: Icon) -> Self {
         self.icon = Ok(icon);
         self
@@ -123,6 +184,36 @@ where
         self
     }
 
+    /// Set attention icon (KDE only)
+    ///
+    /// Shown by the tray instead of the icon when status is
+    /// `TrayIconStatus::NeedsAttention`. Can be a pixmap or a theme icon
+    /// created with `Icon::from_theme_name`.
+    pub fn attention_icon(mut self, icon: Icon) -> Self {
+        self.attention_icon = Some(icon);
+        self
+    }
+
+    /// Set attention animation (KDE only)
+    ///
+    /// Theme name or path of an animation shown when status is
+    /// `TrayIconStatus::NeedsAttention`, trays not supporting animations use
+    /// the attention icon instead.
+    pub fn attention_movie_name(mut self, name: &str) -> Self {
+        self.attention_movie_name = Some(name.to_string());
+        self
+    }
+
+    /// Set icon theme path (Linux only)
+    ///
+    /// Additional directory the tray searches for the icons created with
+    /// `Icon::from_theme_name`, for shipping own icon theme with the
+    /// application.
+    pub fn icon_theme_path(mut self, path: &str) -> Self {
+        self.icon_theme_path = Some(path.to_string());
+        self
+    }
+
     pub fn menu(mut self, menu: MenuBuilder<T>) -> Self
     where
         T: PartialEq + Clone + 'static,
@@ -132,6 +223,50 @@ where
     }
 
     pub fn build(self) -> Result<TrayIcon<T>, Error> {
+        if let Some(menu) = &self.menu {
+            menu.check_unique_ids()?;
+        }
         Ok(TrayIcon::new(crate::build_trayicon(&self)?, self))
     }
+
+    /// Build without blocking, for async code
+    ///
+    /// On Linux `build` waits for D-Bus with `block_on`, which can't be done
+    /// inside an async runtime.
+    #[cfg(feature = "async")]
+    pub async fn build_async(self) -> Result<TrayIcon<T>, Error> {
+        if let Some(menu) = &self.menu {
+            menu.check_unique_ids()?;
+        }
+        Ok(TrayIcon::new(
+            crate::build_trayicon_async(&self).await?,
+            self,
+        ))
+    }
+
+    /// Build with events delivered to the returned receiver
+    ///
+    /// Alternative to `sender`, replaces the sender if one is set.
+    pub fn build_with_receiver(mut self) -> Result<(TrayIcon<T>, Receiver<T>), Error> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.sender = Some(TrayIconSender::new(move |e: &T| {
+            let _ = sender.send(e.clone());
+        }));
+        Ok((self.build()?, receiver))
+    }
+
+    /// Build with events delivered to the returned stream
+    ///
+    /// Alternative to `sender` for async code, replaces the sender if one is
+    /// set.
+    #[cfg(feature = "async")]
+    pub async fn build_with_stream(
+        mut self,
+    ) -> Result<(TrayIcon<T>, impl futures::Stream<Item = T>), Error> {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        self.sender = Some(TrayIconSender::new(move |e: &T| {
+            let _ = sender.unbounded_send(e.clone());
+        }));
+        Ok((self.build_async().await?, receiver))
+    }
 }
@@ -16,6 +16,32 @@ impl<T> TrayIconSender<T> {
     }
 }
 
+/// Event handler, maps the argument such as a click to an event
+pub(crate) struct Handler<A, T>(std::sync::Arc<dyn Fn(A) -> Option<T> + Send + Sync>);
+
+impl<A, T> Clone for Handler<A, T> {
+    fn clone(&self) -> Self {
+        Handler(self.0.clone())
+    }
+}
+
+impl<A, T> std::fmt::Debug for Handler<A, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handler").field("0", &"<function>").finish()
+    }
+}
+
+impl<A, T> Handler<A, T> {
+    pub(crate) fn new(f: impl Fn(A) -> Option<T> + Send + Sync + 'static) -> Self {
+        Handler(std::sync::Arc::new(f))
+    }
+
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) fn event(&self, arg: A) -> Option<T> {
+        self.0(arg)
+    }
+}
+
 impl<T> TrayIconSender<T>
 where
     T: PartialEq + Clone + 'static,